@@ -70,6 +70,21 @@ fn parser_benchmarks(c: &mut Criterion) {
         })
     });
 
+    c.bench_function("decoding_bee_indices", |b| {
+        let bee = include_bytes!("test/bee.gif");
+
+        let mut gif = GifFrameStreamer::from_slice(bee).unwrap();
+        let mut indices = vec![0; gif.base_size().width as usize * gif.base_size().height as usize];
+
+        b.iter(|| {
+            for _ in 0..gif.num_images() {
+                let mut frame = gif.next_frame().unwrap();
+                frame.decode_indices(&mut indices).unwrap();
+            }
+            gif.reset()
+        })
+    });
+
     c.bench_function("drawing_bee_buffer", |b| {
         let crab = include_bytes!("test/bee.gif");
 
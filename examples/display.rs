@@ -13,8 +13,13 @@ use embedded_graphics::{
 use embedded_graphics_simulator::{
     OutputSettings, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
 };
-use std::{fs, num::NonZeroU32, path::PathBuf};
-use tinygif::GifFrameStreamer;
+use std::{
+    fs,
+    num::NonZeroU32,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use tinygif::{GifFrameStreamer, Loops, Player};
 
 #[derive(Parser)]
 struct Args {
@@ -27,21 +32,29 @@ struct Args {
 }
 
 fn display_gif(data: &[u8], settings: &OutputSettings) {
-    let mut gif = GifFrameStreamer::from_slice(&data).unwrap();
+    let gif = GifFrameStreamer::from_slice(&data).unwrap();
 
     let mut display = SimulatorDisplay::<Rgb565>::new(gif.size());
+    let mut player = Player::new(gif);
+    player.set_loops(Loops::Forever);
 
     let mut window = Window::new("GIF viewer", &settings);
+    let start = Instant::now();
     loop {
-        gif.seek_to_next_frame().unwrap();
-        Image::new(&gif, Point::zero())
-            .draw(&mut display.color_converted())
-            .unwrap();
+        let tick = player.tick(start.elapsed().as_millis() as u64).unwrap();
+        if tick.redraw {
+            Image::new(player.streamer(), Point::zero())
+                .draw(&mut display.color_converted())
+                .unwrap();
+        }
         window.update(&display);
         if window.events().any(|e| e == SimulatorEvent::Quit) {
             return;
         }
-        std::thread::sleep(std::time::Duration::from_millis(gif.delay_ms() as u64));
+        if let Some(deadline) = tick.next_deadline {
+            let deadline = start + Duration::from_millis(deadline);
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        }
     }
 }
 
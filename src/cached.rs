@@ -0,0 +1,171 @@
+use embedded_graphics::{
+    draw_target::DrawTarget, pixelcolor::Rgb888, prelude::Point, primitives::Rectangle, Pixel,
+};
+
+/// Palette indices of a frame that was decoded once with [`GifFrame::cache_indices`]
+///
+/// Drawing a cached frame doesn't touch the LZW stream, so it can be redrawn as often as needed,
+/// each time through a different palette: colour cycling, fades or theming only cost the drawing
+/// itself. Pixels that use the frame's transparent index are never drawn.
+///
+/// [`GifFrame::cache_indices`]: crate::GifFrame::cache_indices
+#[derive(Debug, Copy, Clone)]
+pub struct CachedFrame<'buf> {
+    indices: &'buf [u8],
+    frame_area: Rectangle,
+    clipped_area: Rectangle,
+    transparent_idx: Option<u8>,
+}
+
+impl<'buf> CachedFrame<'buf> {
+    pub(crate) fn new(
+        indices: &'buf [u8],
+        frame_area: Rectangle,
+        clipped_area: Rectangle,
+        transparent_idx: Option<u8>,
+    ) -> Self {
+        Self {
+            indices,
+            frame_area,
+            clipped_area,
+            transparent_idx,
+        }
+    }
+
+    /// Row-major palette indices covering [`Self::frame_area`]
+    pub fn indices(&self) -> &'buf [u8] {
+        self.indices
+    }
+
+    /// Area the frame declares it covers
+    pub fn frame_area(&self) -> Rectangle {
+        self.frame_area
+    }
+
+    /// Part of [`Self::frame_area`] that is drawn
+    pub fn clipped_area(&self) -> Rectangle {
+        self.clipped_area
+    }
+
+    /// Palette index that is left undrawn, if any
+    pub fn transparent_index(&self) -> Option<u8> {
+        self.transparent_idx
+    }
+
+    /// Draws the frame, looking every index up in `palette`
+    ///
+    /// Indices past the end of `palette` are left undrawn.
+    pub fn draw_with_palette<D>(&self, target: &mut D, palette: &[D::Color]) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+    {
+        self.draw_mapped(target, |idx| palette.get(idx as usize).copied())
+    }
+
+    /// Draws the frame after running every entry of `palette` through `transform` once
+    ///
+    /// `transform` is called with each entry's index and color, so it can implement anything
+    /// from a brightness fade to rotating a range of entries. Indices past the end of `palette`
+    /// are left undrawn.
+    pub fn draw_with_transform<D, F>(
+        &self,
+        target: &mut D,
+        palette: &[Rgb888],
+        mut transform: F,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        F: FnMut(u8, Rgb888) -> D::Color,
+    {
+        let mut table: [Option<D::Color>; 256] = [None; 256];
+        for (idx, (entry, color)) in table.iter_mut().zip(palette).enumerate() {
+            *entry = Some(transform(idx as u8, *color));
+        }
+        self.draw_mapped(target, |idx| table[idx as usize])
+    }
+
+    fn draw_mapped<D, F>(&self, target: &mut D, mut color: F) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        F: FnMut(u8) -> Option<D::Color>,
+    {
+        let width = self.frame_area.size.width as usize;
+        if width == 0 {
+            return Ok(());
+        }
+        let origin = self.frame_area.top_left;
+        let clipped_area = self.clipped_area;
+        let transparent_idx = self.transparent_idx;
+
+        let pixels = self
+            .indices
+            .iter()
+            .enumerate()
+            .filter(|(_, &idx)| Some(idx) != transparent_idx)
+            .filter_map(|(n, &idx)| {
+                let point = origin + Point::new((n % width) as i32, (n / width) as i32);
+                if !clipped_area.contains(point) {
+                    return None;
+                }
+                color(idx).map(|color| Pixel(point, color))
+            });
+        target.draw_iter(pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{encode_gif, Framebuffer, TestFrame, PALETTE};
+    use crate::GifFrameStreamer;
+    use embedded_graphics::{
+        pixelcolor::Rgb565,
+        prelude::{RgbColor, Size},
+    };
+
+    #[test]
+    fn redraw_with_other_palettes() {
+        let mut frame = TestFrame::new((2, 2), vec![1, 2, 3, 0]);
+        frame.origin = (1, 1);
+        frame.transparent = Some(0);
+        let gif = encode_gif((3, 3), &PALETTE, &[frame]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut buf = [0; 4];
+        let cached = frames
+            .next_frame()
+            .unwrap()
+            .cache_indices(&mut buf)
+            .unwrap();
+        assert_eq!(cached.indices(), [1, 2, 3, 0]);
+        assert_eq!(cached.transparent_index(), Some(0));
+        assert_eq!(
+            cached.frame_area(),
+            Rectangle::new(Point::new(1, 1), Size::new(2, 2))
+        );
+
+        let mut fb = Framebuffer::<3, 3>::new();
+        let inverted = [Rgb565::WHITE, Rgb565::CYAN, Rgb565::MAGENTA, Rgb565::YELLOW];
+        cached.draw_with_palette(&mut fb, &inverted).unwrap();
+        assert_eq!(fb.pixel(Point::new(1, 1)), Rgb565::CYAN);
+        assert_eq!(fb.pixel(Point::new(2, 1)), Rgb565::MAGENTA);
+        assert_eq!(fb.pixel(Point::new(1, 2)), Rgb565::YELLOW);
+        // Transparent, so the background shows through
+        assert_eq!(fb.pixel(Point::new(2, 2)), Rgb565::BLACK);
+
+        // Rotate the palette by one entry
+        let palette: Vec<Rgb888> = PALETTE
+            .iter()
+            .map(|&[r, g, b]| Rgb888::new(r, g, b))
+            .collect();
+        cached
+            .draw_with_transform(&mut fb, &palette, |idx, _| {
+                Rgb565::from(palette[(idx as usize + 1) % palette.len()])
+            })
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(1, 1)), Rgb565::GREEN);
+        assert_eq!(fb.pixel(Point::new(2, 1)), Rgb565::BLUE);
+        assert_eq!(fb.pixel(Point::new(1, 2)), Rgb565::BLACK);
+        assert_eq!(fb.pixel(Point::new(2, 2)), Rgb565::BLACK);
+    }
+}
@@ -61,7 +61,7 @@ impl Block {
 }
 
 /// Errors that emerge when parsing our gif file
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ParseError {
     ///Malformed GIF file
     BadGifFile,
@@ -75,5 +75,11 @@ pub enum ParseError {
     ///rewindable iterators
     SeekFail,
     ///No images left
-    NoImagesLeft
+    NoImagesLeft,
+    ///LZW stream produced an index that is not present in the frame's color table
+    PaletteIndexOutOfRange,
+    ///LZW stream of a frame is malformed
+    BadLzwData,
+    ///Caller provided buffer is too small to hold the decoded data
+    BufferTooSmall,
 }
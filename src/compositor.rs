@@ -0,0 +1,364 @@
+use embedded_graphics::{draw_target::DrawTarget, pixelcolor::Rgb565, primitives::Rectangle};
+
+use crate::{
+    common::ParseError, header::DisposalMethod, iterators::SeekableIter, lzw::LzwDecoder, GifFrame,
+};
+
+/// Composites the frames of an animation using the [`DrawTarget`] itself as the canvas
+///
+/// Frames are drawn without their transparent pixels, so whatever earlier frames left on the
+/// target shows through. Frames disposed with [`DisposalMethod::OverwriteWithBG`] have their
+/// area filled with the background color right before the next frame is drawn. None of this
+/// needs memory beyond the compositor itself.
+///
+/// [`DisposalMethod::OverwriteWithPrev`] needs the pixels under the frame from before it was
+/// drawn. [`Compositor::draw_frame`] refuses such frames with
+/// [`CompositeError::RestoreToPreviousUnsupported`]; check
+/// [`GifFrameStreamer::restores_to_previous`] up front to find out whether a file is affected.
+/// Targets that can read pixels back can use [`Compositor::draw_frame_restoring`] instead.
+///
+/// [`GifFrameStreamer::restores_to_previous`]: crate::GifFrameStreamer::restores_to_previous
+#[derive(Debug, Clone)]
+pub struct Compositor {
+    background: Rgb565,
+    /// Disposal of the previous frame, carried out right before drawing the next one
+    pending: Option<PendingDisposal>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum PendingDisposal {
+    /// Fill the area with the background
+    Clear(Rectangle),
+    /// Copy the area back from the save buffer
+    Restore(Rectangle),
+}
+
+/// [`DrawTarget`]s that can read back what was drawn to them, e.g. framebuffers or displays
+/// with GRAM readback
+pub trait ReadbackTarget: DrawTarget {
+    /// Reads the pixels of `area` into `buf` in row-major order
+    ///
+    /// `buf` holds exactly as many pixels as `area` does.
+    fn read_area(&mut self, area: &Rectangle, buf: &mut [Self::Color]) -> Result<(), Self::Error>;
+}
+
+/// Reasons a [`Compositor`] couldn't draw a frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompositeError<E> {
+    /// The frame is disposed with [`DisposalMethod::OverwriteWithPrev`]; nothing was drawn
+    RestoreToPreviousUnsupported,
+    /// The frame's image data is malformed, part of it may have been drawn
+    ///
+    /// The frame's area is still disposed of as usual before the next frame is drawn.
+    Decode(ParseError),
+    /// The save buffer can't hold the area under the frame, see
+    /// [`GifFrameStreamer::restore_buffer_len`]; nothing was drawn
+    ///
+    /// [`GifFrameStreamer::restore_buffer_len`]: crate::GifFrameStreamer::restore_buffer_len
+    SaveBufferTooSmall,
+    /// Drawing to the target failed
+    Draw(E),
+}
+
+impl Compositor {
+    /// Creates a compositor that clears disposed frames to `background`
+    ///
+    /// [`GifFrameStreamer::background_color`] is the color the file asks for, though browsers
+    /// clear to transparent instead, i.e. whatever is behind the animation.
+    ///
+    /// [`GifFrameStreamer::background_color`]: crate::GifFrameStreamer::background_color
+    pub fn new(background: Rgb565) -> Self {
+        Self {
+            background,
+            pending: None,
+        }
+    }
+
+    /// Color that disposed frames are cleared to
+    pub fn background(&self) -> Rgb565 {
+        self.background
+    }
+
+    /// Forgets the disposal of the last drawn frame, e.g. after clearing the target to start over
+    pub fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    /// Disposes of the previously drawn frame, then draws the rest of `frame` on top of the target
+    pub fn draw_frame<S, const DECODE_BUF: usize, L, D>(
+        &mut self,
+        frame: &mut GifFrame<'_, S, DECODE_BUF, L>,
+        target: &mut D,
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        S: SeekableIter,
+        L: LzwDecoder,
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if frame.disposal_method() == DisposalMethod::OverwriteWithPrev {
+            return Err(CompositeError::RestoreToPreviousUnsupported);
+        }
+        self.composite(frame, target, &mut [], |_, _, _| Ok(()))
+    }
+
+    /// Same as [`Self::draw_frame`], also handling [`DisposalMethod::OverwriteWithPrev`]
+    ///
+    /// The pixels under such frames are read back into `save` before the frame is drawn, and
+    /// copied back when the next frame is drawn; pass the same buffer every time. It must hold
+    /// [`GifFrameStreamer::restore_buffer_len`] pixels, which is 0 for files that never restore
+    /// to the previous contents.
+    ///
+    /// [`GifFrameStreamer::restore_buffer_len`]: crate::GifFrameStreamer::restore_buffer_len
+    pub fn draw_frame_restoring<S, const DECODE_BUF: usize, L, D>(
+        &mut self,
+        frame: &mut GifFrame<'_, S, DECODE_BUF, L>,
+        target: &mut D,
+        save: &mut [Rgb565],
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        S: SeekableIter,
+        L: LzwDecoder,
+        D: ReadbackTarget<Color = Rgb565>,
+    {
+        if frame.disposal_method() == DisposalMethod::OverwriteWithPrev
+            && save.len() < pixel_count(&frame.clipped_area())
+        {
+            return Err(CompositeError::SaveBufferTooSmall);
+        }
+        self.composite(frame, target, save, D::read_area)
+    }
+
+    fn composite<S, const DECODE_BUF: usize, L, D, F>(
+        &mut self,
+        frame: &mut GifFrame<'_, S, DECODE_BUF, L>,
+        target: &mut D,
+        save: &mut [Rgb565],
+        mut read_area: F,
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        S: SeekableIter,
+        L: LzwDecoder,
+        D: DrawTarget<Color = Rgb565>,
+        F: FnMut(&mut D, &Rectangle, &mut [Rgb565]) -> Result<(), D::Error>,
+    {
+        self.dispose(target, save)?;
+
+        let area = frame.clipped_area();
+        let disposal = frame.disposal_method();
+        if disposal == DisposalMethod::OverwriteWithPrev {
+            read_area(target, &area, &mut save[..pixel_count(&area)])
+                .map_err(CompositeError::Draw)?;
+        }
+        let drawn = target.draw_iter(frame.opaque_pixels());
+
+        // Whatever part of the frame made it onto the target is disposed of like the whole frame
+        self.pending = match disposal {
+            DisposalMethod::OverwriteWithBG => Some(PendingDisposal::Clear(area)),
+            DisposalMethod::OverwriteWithPrev => Some(PendingDisposal::Restore(area)),
+            _ => None,
+        };
+        drawn.map_err(CompositeError::Draw)?;
+        frame
+            .error()
+            .map_or(Ok(()), |err| Err(CompositeError::Decode(err)))
+    }
+
+    fn dispose<D>(
+        &mut self,
+        target: &mut D,
+        save: &[Rgb565],
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        match self.pending {
+            Some(PendingDisposal::Clear(area)) => target
+                .fill_solid(&area, self.background)
+                .map_err(CompositeError::Draw)?,
+            Some(PendingDisposal::Restore(area)) => {
+                // Only when switching from `draw_frame_restoring` to `draw_frame`, or to a smaller
+                // buffer; the disposal stays pending
+                let saved = save
+                    .get(..pixel_count(&area))
+                    .ok_or(CompositeError::SaveBufferTooSmall)?;
+                target
+                    .fill_contiguous(&area, saved.iter().copied())
+                    .map_err(CompositeError::Draw)?
+            }
+            None => {}
+        }
+        self.pending = None;
+        Ok(())
+    }
+}
+
+fn pixel_count(area: &Rectangle) -> usize {
+    area.size.width as usize * area.size.height as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{encode_gif, Framebuffer, TestFrame, PALETTE};
+    use crate::GifFrameStreamer;
+    use embedded_graphics::prelude::{Point, RgbColor};
+
+    #[test]
+    fn composite_onto_target() {
+        let mut cleared = TestFrame::new((1, 2), vec![3, 3]);
+        cleared.origin = (1, 0);
+        cleared.disposal = 2;
+        let mut overlay = TestFrame::new((2, 2), vec![0, 2, 0, 0]);
+        overlay.transparent = Some(0);
+        let gif = encode_gif(
+            (2, 2),
+            &PALETTE,
+            &[TestFrame::new((2, 2), vec![1; 4]), cleared, overlay],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        assert!(!frames.restores_to_previous());
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut fb = Framebuffer::<2, 2>::new();
+
+        compositor
+            .draw_frame(&mut frames.next_frame().unwrap(), &mut fb)
+            .unwrap();
+        compositor
+            .draw_frame(&mut frames.next_frame().unwrap(), &mut fb)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(1, 1)), Rgb565::BLUE);
+
+        compositor
+            .draw_frame(&mut frames.next_frame().unwrap(), &mut fb)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(0, 0)), Rgb565::RED);
+        assert_eq!(fb.pixel(Point::new(1, 0)), Rgb565::GREEN);
+        assert_eq!(fb.pixel(Point::new(0, 1)), Rgb565::RED);
+        // Cleared to the background and not covered by the overlay
+        assert_eq!(fb.pixel(Point::new(1, 1)), Rgb565::WHITE);
+    }
+
+    #[test]
+    fn dispose_after_decode_error() {
+        // The last pixel is out of range for the palette
+        let mut broken = TestFrame::new((2, 2), vec![1, 1, 1, 200]);
+        broken.disposal = 2;
+        let gif = encode_gif((2, 2), &PALETTE, &[broken, TestFrame::new((1, 1), vec![3])]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        frames.set_fallback_color(None);
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut fb = Framebuffer::<2, 2>::new();
+
+        let result = compositor.draw_frame(&mut frames.next_frame().unwrap(), &mut fb);
+        assert_eq!(
+            result,
+            Err(CompositeError::Decode(ParseError::PaletteIndexOutOfRange))
+        );
+        assert_eq!(fb.pixel(Point::new(1, 0)), Rgb565::RED);
+
+        compositor
+            .draw_frame(&mut frames.next_frame().unwrap(), &mut fb)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(0, 0)), Rgb565::BLUE);
+        assert_eq!(fb.pixel(Point::new(1, 0)), Rgb565::WHITE);
+        assert_eq!(fb.pixel(Point::new(0, 1)), Rgb565::WHITE);
+    }
+
+    #[test]
+    fn restore_to_previous() {
+        let mut restored = TestFrame::new((1, 2), vec![2, 2]);
+        restored.disposal = 3;
+        let gif = encode_gif(
+            (2, 2),
+            &PALETTE,
+            &[
+                TestFrame::new((2, 2), vec![1, 3, 3, 1]),
+                restored,
+                TestFrame::new((1, 1), vec![0]),
+            ],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        assert_eq!(frames.restore_buffer_len(), 2);
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut fb = Framebuffer::<2, 2>::new();
+        let mut save = [Rgb565::BLACK; 2];
+
+        frames.seek_to_next_frame().unwrap();
+        compositor
+            .draw_frame_restoring(
+                &mut frames.current_frame().unwrap(),
+                &mut fb,
+                &mut save[..1],
+            )
+            .unwrap();
+        frames.seek_to_next_frame().unwrap();
+        let result = compositor.draw_frame_restoring(
+            &mut frames.current_frame().unwrap(),
+            &mut fb,
+            &mut save[..1],
+        );
+        assert_eq!(result, Err(CompositeError::SaveBufferTooSmall));
+
+        compositor
+            .draw_frame_restoring(&mut frames.current_frame().unwrap(), &mut fb, &mut save)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(0, 0)), Rgb565::GREEN);
+        assert_eq!(fb.pixel(Point::new(0, 1)), Rgb565::GREEN);
+
+        compositor
+            .draw_frame_restoring(&mut frames.next_frame().unwrap(), &mut fb, &mut save)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(0, 0)), Rgb565::BLACK);
+        // Restored to what the first frame drew
+        assert_eq!(fb.pixel(Point::new(0, 1)), Rgb565::BLUE);
+        assert_eq!(fb.pixel(Point::new(1, 0)), Rgb565::BLUE);
+    }
+
+    #[test]
+    fn restore_buffer_matches_drawn_area() {
+        let mut restored = TestFrame::new((2, 2), vec![2; 4]);
+        restored.origin = (1, 1);
+        restored.disposal = 3;
+        let gif = encode_gif(
+            (2, 2),
+            &PALETTE,
+            &[restored, TestFrame::new((1, 1), vec![1])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        assert_eq!(frames.restore_buffer_len(), 1);
+        frames.set_clip_to_screen(false);
+        assert_eq!(frames.restore_buffer_len(), 4);
+
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut fb = Framebuffer::<3, 3>::new();
+        let mut save = vec![Rgb565::BLACK; frames.restore_buffer_len()];
+        compositor
+            .draw_frame_restoring(&mut frames.next_frame().unwrap(), &mut fb, &mut save)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(2, 2)), Rgb565::GREEN);
+
+        // The restore can't be skipped, so it stays pending until there is a buffer to do it from
+        let result = compositor.draw_frame(&mut frames.next_frame().unwrap(), &mut fb);
+        assert_eq!(result, Err(CompositeError::SaveBufferTooSmall));
+        assert_eq!(fb.pixel(Point::new(0, 0)), Rgb565::BLACK);
+        compositor
+            .draw_frame_restoring(&mut frames.current_frame().unwrap(), &mut fb, &mut save)
+            .unwrap();
+        assert_eq!(fb.pixel(Point::new(2, 2)), Rgb565::BLACK);
+        assert_eq!(fb.pixel(Point::new(0, 0)), Rgb565::RED);
+    }
+
+    #[test]
+    fn restore_to_previous_is_refused() {
+        let mut restored = TestFrame::new((1, 1), vec![2]);
+        restored.disposal = 3;
+        let gif = encode_gif((1, 1), &PALETTE, &[restored]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        assert!(frames.restores_to_previous());
+
+        let mut fb = Framebuffer::<1, 1>::new();
+        let result =
+            Compositor::new(Rgb565::BLACK).draw_frame(&mut frames.next_frame().unwrap(), &mut fb);
+        assert_eq!(result, Err(CompositeError::RestoreToPreviousUnsupported));
+    }
+}
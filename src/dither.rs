@@ -0,0 +1,253 @@
+use embedded_graphics::{
+    pixelcolor::{BinaryColor, Gray2, Gray4, Gray8, Rgb888},
+    prelude::{PixelColor, Point, RgbColor},
+};
+
+use crate::common::ParseError;
+
+/// Colors made up of evenly spaced shades from black to white, that frames can be reduced to
+pub trait LumaColor: PixelColor {
+    /// Luma of the brightest shade, the darkest one is always 0
+    const MAX_LUMA: u8;
+    /// Color of shade `luma`, which is at most [`Self::MAX_LUMA`]
+    fn from_luma(luma: u8) -> Self;
+}
+
+impl LumaColor for BinaryColor {
+    const MAX_LUMA: u8 = 1;
+    fn from_luma(luma: u8) -> Self {
+        BinaryColor::from(luma != 0)
+    }
+}
+
+impl LumaColor for Gray2 {
+    const MAX_LUMA: u8 = 3;
+    fn from_luma(luma: u8) -> Self {
+        Gray2::new(luma)
+    }
+}
+
+impl LumaColor for Gray4 {
+    const MAX_LUMA: u8 = 15;
+    fn from_luma(luma: u8) -> Self {
+        Gray4::new(luma)
+    }
+}
+
+impl LumaColor for Gray8 {
+    const MAX_LUMA: u8 = 255;
+    fn from_luma(luma: u8) -> Self {
+        Gray8::new(luma)
+    }
+}
+
+/// How colors are reduced to the few shades of a [`LumaColor`]
+pub enum Dither<'a> {
+    /// Every pixel picks a shade on its own, with shades switching over once the pixel is
+    /// `threshold` of the way from one shade to the next; 128 rounds to the nearest shade
+    ///
+    /// Pixels that are exactly on a shade always keep it, so a threshold of 0 works like 1.
+    Threshold(u8),
+    /// Ordered dithering with an 8x8 Bayer matrix, aligned to the logical screen so that frames
+    /// at different origins line up
+    ///
+    /// Each pixel only depends on its own color and position, so nothing is buffered.
+    Ordered,
+    /// Floyd-Steinberg error diffusion, spreading each pixel's error to its unprocessed neighbours
+    ///
+    /// The slice holds the error of the current and the next row and must be at least
+    /// `2 * (width + 2)` entries long for a frame `width` pixels wide. Interlaced frames spread
+    /// the error to the next row in decode order rather than the one below.
+    ErrorDiffusion(&'a mut [i16]),
+}
+
+/// 8x8 Bayer matrix, with thresholds from 0 to 63
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Perceived brightness of `color`, using the Rec. 601 weights
+pub(crate) fn luma(color: Rgb888) -> u8 {
+    let weighted = 77 * color.r() as u32 + 150 * color.g() as u32 + 29 * color.b() as u32;
+    (weighted >> 8) as u8
+}
+
+/// Amount added to `value * max` before dividing by 255 to pick a level for the pixel at `point`,
+/// between 2 and 254 so that the result never exceeds `max`
+pub(crate) fn bayer_offset(point: Point) -> u32 {
+    BAYER[(point.y & 7) as usize][(point.x & 7) as usize] as u32 * 4 + 2
+}
+
+/// Picks one of `max + 1` evenly spaced levels for `value`, with `offset` as explained at
+/// [`bayer_offset`]
+#[inline]
+pub(crate) fn quantize(value: u8, max: u8, offset: u32) -> u8 {
+    ((value as u32 * max as u32 + offset) / 255) as u8
+}
+
+/// Reduces `color` to `C` with ordered dithering at screen position `point`
+///
+/// Each channel is quantized to `C`'s depth against the same Bayer threshold, which spreads the
+/// rounding error of gradients over neighbouring pixels instead of producing bands.
+pub(crate) fn ordered_rgb<C>(color: Rgb888, point: Point) -> C
+where
+    C: RgbColor + From<Rgb888>,
+{
+    let offset = bayer_offset(point);
+    // Scales the level back up to 8 bits, rounding up so that converting to `C` gives it back
+    let channel = |value, max: u8| {
+        let level = quantize(value, max, offset) as u32;
+        ((level * 255 + max as u32 - 1) / max as u32) as u8
+    };
+    C::from(Rgb888::new(
+        channel(color.r(), C::MAX_R),
+        channel(color.g(), C::MAX_G),
+        channel(color.b(), C::MAX_B),
+    ))
+}
+
+/// Reduces the luma of a frame's pixels, one pixel at a time in decode order
+pub(crate) struct LumaQuantizer<'a> {
+    dither: Dither<'a>,
+    width: usize,
+}
+
+impl<'a> LumaQuantizer<'a> {
+    pub(crate) fn new(dither: Dither<'a>, width: usize) -> Result<Self, ParseError> {
+        if let Dither::ErrorDiffusion(errors) = &dither {
+            if errors.len() < 2 * (width + 2) {
+                return Err(ParseError::BufferTooSmall);
+            }
+        }
+        Ok(Self { dither, width })
+    }
+
+    /// Shade for the pixel with luma `value`, in `column` of the frame and at screen `point`
+    pub(crate) fn quantize<C: LumaColor>(&mut self, point: Point, column: usize, value: u8) -> C {
+        let max = C::MAX_LUMA;
+        let luma = match &mut self.dither {
+            Dither::Threshold(threshold) => {
+                quantize(value, max, (255 - *threshold as u32).min(254))
+            }
+            Dither::Ordered => quantize(value, max, bayer_offset(point)),
+            Dither::ErrorDiffusion(errors) => {
+                let (current, next) = errors[..2 * (self.width + 2)].split_at_mut(self.width + 2);
+                if column == 0 {
+                    current.copy_from_slice(next);
+                    next.iter_mut().for_each(|err| *err = 0);
+                }
+
+                // Errors are stored one entry to the right, so the left neighbour is at `column`
+                let wanted = (value as i16 + current[column + 1]).clamp(0, 255);
+                let luma = quantize(wanted as u8, max, 127);
+                let err = wanted - (luma as u32 * 255 / max as u32) as i16;
+                let (right, below_left, below) = (err * 7 / 16, err * 3 / 16, err * 5 / 16);
+                current[column + 2] += right;
+                next[column] += below_left;
+                next[column + 1] += below;
+                // Whatever rounding left over, so that no error is lost
+                next[column + 2] += err - right - below_left - below;
+                luma
+            }
+        };
+        C::from_luma(luma)
+    }
+
+    /// Forgets any error carried over from previously quantized pixels
+    pub(crate) fn reset(&mut self) {
+        if let Dither::ErrorDiffusion(errors) = &mut self.dither {
+            errors.iter_mut().for_each(|err| *err = 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantize_row<C: LumaColor>(dither: Dither<'_>, values: &[u8]) -> std::vec::Vec<C> {
+        let mut quantizer = LumaQuantizer::new(dither, values.len()).unwrap();
+        quantizer.reset();
+        values
+            .iter()
+            .enumerate()
+            .map(|(x, &value)| quantizer.quantize(Point::new(x as i32, 0), x, value))
+            .collect()
+    }
+
+    #[test]
+    fn threshold() {
+        let values = [0, 100, 127, 128, 255];
+        assert_eq!(
+            quantize_row::<BinaryColor>(Dither::Threshold(128), &values),
+            [
+                BinaryColor::Off,
+                BinaryColor::Off,
+                BinaryColor::Off,
+                BinaryColor::On,
+                BinaryColor::On
+            ]
+        );
+        assert_eq!(
+            quantize_row::<Gray2>(Dither::Threshold(128), &values),
+            [0, 1, 1, 2, 3].map(Gray2::new)
+        );
+        assert_eq!(
+            quantize_row::<Gray8>(Dither::Threshold(128), &values),
+            values.map(Gray8::new)
+        );
+    }
+
+    #[test]
+    fn threshold_extremes() {
+        let values = [0, 1, 254, 255];
+        let binary = |threshold| quantize_row::<BinaryColor>(Dither::Threshold(threshold), &values);
+        assert_eq!(binary(0), [false, true, true, true].map(BinaryColor::from));
+        assert_eq!(
+            binary(255),
+            [false, false, false, true].map(BinaryColor::from)
+        );
+
+        let gray2 = |threshold| quantize_row::<Gray2>(Dither::Threshold(threshold), &values);
+        assert_eq!(gray2(0), [0, 1, 3, 3].map(Gray2::new));
+        assert_eq!(gray2(255), [0, 0, 2, 3].map(Gray2::new));
+
+        for threshold in [0, 255] {
+            assert_eq!(
+                quantize_row::<Gray8>(Dither::Threshold(threshold), &values),
+                values.map(Gray8::new)
+            );
+        }
+    }
+
+    #[test]
+    fn dithering_keeps_average_brightness() {
+        let mut errors = [0; 2 * (8 + 2)];
+        for dither in [Dither::Ordered, Dither::ErrorDiffusion(&mut errors)] {
+            let mut quantizer = LumaQuantizer::new(dither, 8).unwrap();
+            quantizer.reset();
+            let mut on = 0;
+            for y in 0..8 {
+                for x in 0..8 {
+                    let color: BinaryColor = quantizer.quantize(Point::new(x, y), x as usize, 64);
+                    on += color.is_on() as usize;
+                }
+            }
+            // A quarter of 64 pixels at a luma of 64 / 255
+            assert!((15..=17).contains(&on), "{} pixels on", on);
+        }
+    }
+
+    #[test]
+    fn error_buffer_too_small() {
+        let mut errors = [0; 7];
+        assert!(LumaQuantizer::new(Dither::ErrorDiffusion(&mut errors), 2).is_err());
+    }
+}
@@ -1,28 +1,81 @@
 use embedded_graphics::{
-    pixelcolor::Rgb565,
-    prelude::{Point, Size},
+    draw_target::DrawTarget,
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::{Point, RgbColor, Size},
     primitives::Rectangle,
+    Pixel,
 };
 
-use core::cell::{RefCell, RefMut};
-
-use giflzw::{Decoder, LzwStatus};
+use core::{
+    cell::{RefCell, RefMut},
+    marker::PhantomData,
+};
 
 use crate::{
+    cached::CachedFrame,
     common::ParseError,
-    header::{ColorTable, GifInfo, LocalImageDescriptor},
+    compositor::{CompositeError, Compositor, ReadbackTarget},
+    dither::{self, Dither, LumaColor, LumaQuantizer},
+    header::{ColorTable, DisposalMethod, GifInfo, GraphicsControl, LocalImageDescriptor},
     iterators::{ByteIterator, SeekableIter, SeekableSliceIter},
+    lzw::{BufferResult, Decoder, LzwDecoder, LzwStatus},
+    palette::{NoOverrides, NoTransform, PaletteOverrides, PaletteRemap, PaletteTransform},
 };
 
-pub struct GifFrameStreamer<S: SeekableIter> {
+/// Size of the buffer that LZW output is decoded into when none is specified
+pub const DEFAULT_DECODE_BUFFER_SIZE: usize = 1024;
+
+/// Streams the frames of a GIF file out of a [`SeekableIter`]
+///
+/// The streamer takes up about 17 KiB on its own, regardless of `DECODE_BUF`. Most of that is the
+/// string table of the default [`Decoder`] at 12 KiB. The rest is an index of 16 bytes per frame
+/// with room for 128 frames inline, longer files spilling to the heap, and the global color table
+/// and that of the current frame at about 1.3 KiB each: 768 bytes for the RGB triples, 512 for
+/// their [`Rgb565`] conversion and a few words of bookkeeping.
+///
+/// `DECODE_BUF` is the size in bytes of the buffer that each [`GifFrame`] decodes LZW output into,
+/// and makes up most of a frame's ~`DECODE_BUF + 400` byte footprint. Smaller buffers save RAM at
+/// the cost of speed, since the sub-block and decoder state machine runs once per buffer refill;
+/// larger buffers refill less often. An empty buffer could never be refilled, so a `DECODE_BUF` of
+/// 0 is rejected at compile time. The default is [`DEFAULT_DECODE_BUFFER_SIZE`], use
+/// [`GifFrameStreamer::from_slice_with_buffer`] or [`GifFrameStreamer::new_with_buffer`] to pick
+/// another size.
+///
+/// `L` is the [`LzwDecoder`] that image data is run through, the crate's own [`Decoder`] unless
+/// another one is passed to [`GifFrameStreamer::with_decoder`].
+///
+/// `T` is the [`PaletteTransform`] that color tables are run through as they're loaded, see
+/// [`GifFrameStreamer::with_palette_transform`].
+///
+/// `P` are the [`PaletteOverrides`] used instead of the file's color tables, none unless they are
+/// set with [`GifFrameStreamer::with_palette_overrides`].
+pub struct GifFrameStreamer<
+    S: SeekableIter,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L: LzwDecoder = Decoder,
+    T: PaletteTransform = NoTransform,
+    P: PaletteOverrides = NoOverrides,
+> {
     pub(crate) header_info: GifInfo,
     frame_offset: usize,
-    decoder: RefCell<Decoder>,
+    /// Index of the frame that `local_image_descriptor` belongs to
+    current_index: Option<usize>,
+    decoder: RefCell<L>,
     local_image_descriptor: Option<LocalImageDescriptor>,
     bytes: ByteIterator<S>,
+    fallback_color: Option<Rgb565>,
+    clip_to_screen: bool,
+    transform: T,
+    palette_overrides: P,
 }
 
-impl<S: SeekableIter> GifFrameStreamer<S> {
+impl<S, const DECODE_BUF: usize, L, T, P> GifFrameStreamer<S, DECODE_BUF, L, T, P>
+where
+    S: SeekableIter,
+    L: LzwDecoder,
+    T: PaletteTransform,
+    P: PaletteOverrides,
+{
     pub fn num_images(&self) -> usize {
         self.header_info.num_images()
     }
@@ -32,15 +85,265 @@ impl<S: SeekableIter> GifFrameStreamer<S> {
         self.header_info.delay_time()
     }
 
+    /// Time to show the `idx`th frame for, in milliseconds, if the frame specifies it
+    pub fn frame_delay_ms(&self, idx: usize) -> Option<u32> {
+        self.header_info.frame_delay(idx)
+    }
+
+    /// Number of times the animation repeats after playing once, where 0 means forever
+    ///
+    /// This comes from the NETSCAPE2.0 application extension; files without it are meant to play
+    /// once and return `None`.
+    pub fn loop_count(&self) -> Option<u16> {
+        self.header_info.loop_count()
+    }
+
+    /// Index of the frame that [`Self::current_frame`] returns, if a frame was seeked to
+    pub fn frame_index(&self) -> Option<usize> {
+        self.current_index
+    }
+
     pub fn base_size(&self) -> Size {
         self.header_info.base_size()
     }
 
+    /// Color of the background index in the global color table, if there is a global table
+    pub fn background_color(&self) -> Option<Rgb565> {
+        self.header_info.background_color()
+    }
+
+    /// Whether any frame uses [`DisposalMethod::OverwriteWithPrev`], which a
+    /// [`Compositor`](crate::Compositor) can't do without somewhere to save the covered pixels
+    pub fn restores_to_previous(&self) -> bool {
+        self.header_info.restores_to_previous()
+    }
+
+    /// Length of the save buffer that [`Compositor::draw_frame_restoring`] needs for this file,
+    /// the drawn size of its largest frame that is restored to the previous contents
+    ///
+    /// Frames are measured the way they are drawn, so this depends on
+    /// [`Self::set_clip_to_screen`].
+    ///
+    /// [`Compositor::draw_frame_restoring`]: crate::Compositor::draw_frame_restoring
+    pub fn restore_buffer_len(&self) -> usize {
+        self.header_info.max_restore_pixels(self.clip_to_screen)
+    }
+
+    /// Area of the screen that changes when the `idx`th frame is drawn after the one before it
+    ///
+    /// This is the frame's own area, joined with that of the previous frame if it is cleared to
+    /// the background or restored to the previous contents, and clipped to the screen. Drivers
+    /// can limit display updates to it. The first frame is drawn on a cleared screen, which
+    /// changes all of it.
+    pub fn dirty_area(&self, idx: usize) -> Result<Rectangle, ParseError> {
+        let screen = Rectangle::new(Point::zero(), self.base_size());
+        let frame_area = |idx| {
+            self.header_info
+                .frame_area(idx)
+                .map(|area| area.intersection(&screen))
+        };
+        let area = frame_area(idx).ok_or(ParseError::NoImagesLeft)?;
+        if idx == 0 {
+            return Ok(screen);
+        }
+        let disposed = matches!(
+            self.header_info
+                .frame_control(idx - 1)
+                .as_ref()
+                .map(GraphicsControl::disposal_method),
+            Some(DisposalMethod::OverwriteWithBG | DisposalMethod::OverwriteWithPrev)
+        );
+        if !disposed {
+            return Ok(area);
+        }
+        let disposed_area = frame_area(idx - 1).ok_or(ParseError::NoImagesLeft)?;
+        Ok(bounding_union(&area, &disposed_area))
+    }
+
+    /// Whether drawing can start over at the `idx`th frame on a screen cleared to the background
+    ///
+    /// Key frames are the first frame, frames that follow one cleared to the background across
+    /// the whole screen, and frames that cover the whole screen without transparency. Frames of
+    /// the last kind that are restored to the previous contents don't count, restoring them
+    /// needs the screen from before they were drawn. Neither the key frame nor the frames after
+    /// it need any of the frames before it.
+    pub fn is_key_frame(&self, idx: usize) -> bool {
+        self.header_info.is_key_frame(idx)
+    }
+
+    /// Index of the closest key frame at or before `idx`, see [`Self::is_key_frame`]
+    pub fn key_frame_before(&self, idx: usize) -> usize {
+        (0..=idx.min(self.num_images().saturating_sub(1)))
+            .rev()
+            .find(|&idx| self.is_key_frame(idx))
+            .unwrap_or(0)
+    }
+
+    /// Whether the `idx`th frame is restored to the previous contents once it has been shown
+    fn restores_previous(&self, idx: usize) -> bool {
+        matches!(
+            self.header_info.frame_control(idx),
+            Some(control) if control.disposal_method() == DisposalMethod::OverwriteWithPrev
+        )
+    }
+
     pub fn reset(&mut self) -> Result<(), ParseError> {
         self.bytes.seek_to(0)?;
         self.frame_offset = 0;
+        self.current_index = None;
+        self.local_image_descriptor = None;
+        Ok(())
+    }
+
+    /// Sets the color drawn for palette indices that are out of range for the frame's color table
+    ///
+    /// Browsers render such pixels using a fixed color rather than rejecting the file, so this
+    /// defaults to black. Passing `None` instead treats an out of range index as a decode error:
+    /// the frame stops yielding pixels and [`GifFrame::error`] reports
+    /// [`ParseError::PaletteIndexOutOfRange`].
+    pub fn set_fallback_color(&mut self, color: Option<Rgb565>) {
+        self.fallback_color = color;
+    }
+
+    /// Controls whether frame output is clipped to the logical screen, enabled by default
+    ///
+    /// Frames are free to declare an origin and size that extends past [`Self::base_size`]; when
+    /// clipping is disabled, those pixels are drawn outside of the image's declared area.
+    pub fn set_clip_to_screen(&mut self, clip: bool) {
+        self.clip_to_screen = clip;
+    }
+
+    /// Runs every color table through `transform` from now on, see [`PaletteTransform`]
+    ///
+    /// This can change the transform's type, e.g. to go from the default [`NoTransform`] to a
+    /// closure. The global color table and that of the current frame are re-read from the source
+    /// right away.
+    pub fn with_palette_transform<U: PaletteTransform>(
+        self,
+        transform: U,
+    ) -> Result<GifFrameStreamer<S, DECODE_BUF, L, U, P>, ParseError> {
+        let mut streamer = GifFrameStreamer {
+            header_info: self.header_info,
+            frame_offset: self.frame_offset,
+            current_index: self.current_index,
+            decoder: self.decoder,
+            local_image_descriptor: self.local_image_descriptor,
+            bytes: self.bytes,
+            fallback_color: self.fallback_color,
+            clip_to_screen: self.clip_to_screen,
+            transform,
+            palette_overrides: self.palette_overrides,
+        };
+        streamer.reload_color_tables()?;
+        Ok(streamer)
+    }
+
+    /// Replaces the palette transform with another one of the same type, e.g. to change the
+    /// brightness level at runtime
+    ///
+    /// Just like [`Self::with_palette_transform`], the loaded color tables are re-read.
+    pub fn set_palette_transform(&mut self, transform: T) -> Result<(), ParseError> {
+        self.transform = transform;
+        self.reload_color_tables()
+    }
+
+    /// Transform that color tables are run through
+    pub fn palette_transform(&self) -> &T {
+        &self.transform
+    }
+
+    /// Uses `overrides` instead of the file's color tables from now on, see [`PaletteOverrides`]
+    ///
+    /// This can change the type of the overrides, e.g. to go from the default [`NoOverrides`] to
+    /// [`BorrowedPalettes`]. The global color table and that of the current frame are reloaded
+    /// right away.
+    ///
+    /// [`BorrowedPalettes`]: crate::BorrowedPalettes
+    pub fn with_palette_overrides<Q: PaletteOverrides>(
+        self,
+        overrides: Q,
+    ) -> Result<GifFrameStreamer<S, DECODE_BUF, L, T, Q>, ParseError> {
+        let mut streamer = GifFrameStreamer {
+            header_info: self.header_info,
+            frame_offset: self.frame_offset,
+            current_index: self.current_index,
+            decoder: self.decoder,
+            local_image_descriptor: self.local_image_descriptor,
+            bytes: self.bytes,
+            fallback_color: self.fallback_color,
+            clip_to_screen: self.clip_to_screen,
+            transform: self.transform,
+            palette_overrides: overrides,
+        };
+        streamer.reload_color_tables()?;
+        Ok(streamer)
+    }
+
+    /// Replaces the palette overrides with others of the same type, e.g. to switch themes at
+    /// runtime
+    ///
+    /// Just like [`Self::with_palette_overrides`], the loaded color tables are reloaded.
+    pub fn set_palette_overrides(&mut self, overrides: P) -> Result<(), ParseError> {
+        self.palette_overrides = overrides;
+        self.reload_color_tables()
+    }
+
+    /// Palettes that are used instead of the file's color tables
+    pub fn palette_overrides(&self) -> &P {
+        &self.palette_overrides
+    }
+
+    fn reload_color_tables(&mut self) -> Result<(), ParseError> {
+        match self.palette_overrides.palette(None) {
+            Some(colors) => {
+                let table = ColorTable::from_colors(colors, &self.transform);
+                self.header_info.set_global_table(table);
+            }
+            None => self
+                .header_info
+                .reload_global_table(&mut self.bytes, &self.transform)?,
+        }
+        if let Some(idx) = self.current_index {
+            self.load_frame(idx)?;
+        }
+        Ok(())
+    }
+
+    /// Parses the image descriptor of the `idx`th frame, leaving `bytes` at its image data
+    fn load_frame(&mut self, idx: usize) -> Result<(), ParseError> {
+        let offset = self
+            .header_info
+            .frame_offset(idx)
+            .ok_or(ParseError::NoImagesLeft)?;
+        self.bytes.seek_to(offset)?;
+        let mut descriptor = LocalImageDescriptor::parser(&mut self.bytes, &self.transform)?;
+        if let Some(colors) = self.palette_overrides.palette(Some(idx)) {
+            descriptor.set_color_table(ColorTable::from_colors(colors, &self.transform));
+        }
+        self.local_image_descriptor = Some(descriptor);
+        self.current_index = Some(idx);
         Ok(())
     }
+
+    /// Makes the `idx`th frame the current one, wrapping around past the last frame just like
+    /// [`Self::seek_to_next_frame`]
+    pub(crate) fn seek_to_index(&mut self, idx: usize) -> Result<(), ParseError> {
+        self.frame_offset = idx;
+        self.seek_to_next_frame()
+    }
+}
+
+/// Smallest rectangle containing both `a` and `b`, ignoring empty ones
+fn bounding_union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    if a.is_zero_sized() {
+        return *b;
+    } else if b.is_zero_sized() {
+        return *a;
+    }
+    let top_left = a.top_left.component_min(b.top_left);
+    let bottom_right = (a.top_left + a.size).component_max(b.top_left + b.size);
+    let size = bottom_right - top_left;
+    Rectangle::new(top_left, Size::new(size.x as u32, size.y as u32))
 }
 
 struct LilQ<const SIZE: usize> {
@@ -50,7 +353,11 @@ struct LilQ<const SIZE: usize> {
 }
 
 impl<const SIZE: usize> LilQ<SIZE> {
+    /// Fails the build for an empty queue, which would never make progress when refilled
+    const NOT_EMPTY: () = assert!(SIZE > 0, "buffer size must be at least 1 byte");
+
     fn new() -> Self {
+        let () = Self::NOT_EMPTY;
         Self {
             buf: [0; SIZE],
             idx: 0,
@@ -85,89 +392,200 @@ impl<'iter> GifFrameStreamer<SeekableSliceIter<'iter>> {
     pub fn from_slice(
         slice: &'iter [u8],
     ) -> Result<GifFrameStreamer<SeekableSliceIter<'iter>>, ParseError> {
+        Self::from_slice_with_buffer(slice)
+    }
+}
+
+impl<'iter, const DECODE_BUF: usize> GifFrameStreamer<SeekableSliceIter<'iter>, DECODE_BUF> {
+    /// Same as [`GifFrameStreamer::from_slice`], decoding into a buffer of `DECODE_BUF` bytes
+    pub fn from_slice_with_buffer(slice: &'iter [u8]) -> Result<Self, ParseError> {
+        Self::from_slice_with_decoder(slice, Decoder::new(8))
+    }
+}
+
+impl<S: SeekableIter> GifFrameStreamer<S> {
+    pub fn new(header_info: GifInfo, bytes: ByteIterator<S>) -> Self {
+        Self::new_with_buffer(header_info, bytes)
+    }
+}
+
+impl<S: SeekableIter, const DECODE_BUF: usize> GifFrameStreamer<S, DECODE_BUF> {
+    /// Same as [`GifFrameStreamer::new`], decoding into a buffer of `DECODE_BUF` bytes
+    pub fn new_with_buffer(header_info: GifInfo, bytes: ByteIterator<S>) -> Self {
+        Self::with_decoder(header_info, bytes, Decoder::new(8))
+    }
+}
+
+impl<'iter, const DECODE_BUF: usize, L: LzwDecoder>
+    GifFrameStreamer<SeekableSliceIter<'iter>, DECODE_BUF, L>
+{
+    /// Same as [`GifFrameStreamer::from_slice`], running image data through `decoder`
+    pub fn from_slice_with_decoder(slice: &'iter [u8], decoder: L) -> Result<Self, ParseError> {
         let mut bytes = ByteIterator::from_slice(slice);
         let header_info = GifInfo::parser(&mut bytes)?;
         bytes.seek_to(0)?;
 
-        Ok(Self {
-            bytes,
-            decoder: RefCell::new(Decoder::new(8)),
-            frame_offset: 0,
-            local_image_descriptor: None,
-            header_info,
-        })
+        Ok(Self::with_decoder(header_info, bytes, decoder))
     }
 }
 
-impl<S: SeekableIter> GifFrameStreamer<S> {
-    pub fn new(header_info: GifInfo, bytes: ByteIterator<S>) -> Self {
+impl<S: SeekableIter, const DECODE_BUF: usize, L: LzwDecoder> GifFrameStreamer<S, DECODE_BUF, L> {
+    /// Same as [`GifFrameStreamer::new`], running image data through `decoder`
+    pub fn with_decoder(header_info: GifInfo, bytes: ByteIterator<S>, decoder: L) -> Self {
+        let () = LilQ::<DECODE_BUF>::NOT_EMPTY;
         Self {
             bytes,
-            decoder: RefCell::new(Decoder::new(8)),
+            decoder: RefCell::new(decoder),
             frame_offset: 0,
+            current_index: None,
             local_image_descriptor: None,
             header_info,
+            fallback_color: Some(Rgb565::BLACK),
+            clip_to_screen: true,
+            transform: NoTransform,
+            palette_overrides: NoOverrides,
         }
     }
+}
 
+impl<S, const DECODE_BUF: usize, L, T, P> GifFrameStreamer<S, DECODE_BUF, L, T, P>
+where
+    S: SeekableIter,
+    L: LzwDecoder,
+    T: PaletteTransform,
+    P: PaletteOverrides,
+{
     pub fn seek_to_next_frame(&mut self) -> Result<(), ParseError> {
         let offset = self
             .header_info
-            .image_block_locations
-            .get(self.frame_offset)
-            .ok_or(ParseError::NoImagesLeft)
-            .cloned();
+            .frame_offset(self.frame_offset)
+            .ok_or(ParseError::NoImagesLeft);
 
         let offset = match offset {
             Ok(offset) => offset,
             Err(_) => {
                 self.frame_offset = 0;
                 self.header_info
-                    .image_block_locations
-                    .get(self.frame_offset)
+                    .frame_offset(self.frame_offset)
                     .ok_or(ParseError::BadGifFile)?
-                    .clone()
             }
         };
 
         self.frame_offset += 1;
 
         self.bytes.seek_to(offset)?;
-        self.local_image_descriptor = Some(LocalImageDescriptor::parser(&mut self.bytes)?);
+        self.load_frame(self.frame_offset - 1)
+    }
+
+    /// Makes the `idx`th frame the current one and composites the screen as it looks with that
+    /// frame shown onto `target`
+    ///
+    /// Frames build on what earlier frames left on the screen, so drawing starts at the nearest
+    /// key frame before `idx`, see [`Self::is_key_frame`]. Unless that frame covers the screen and
+    /// isn't restored to the previous contents, the screen is cleared to the compositor's
+    /// background first. Afterwards, the following frames can be drawn with the same compositor as
+    /// usual.
+    pub fn seek_to_frame<D>(
+        &mut self,
+        idx: usize,
+        compositor: &mut Compositor,
+        target: &mut D,
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        self.composite_up_to(idx, compositor, target, |compositor, frame, target| {
+            compositor.draw_frame(frame, target)
+        })
+    }
+
+    /// Same as [`Self::seek_to_frame`], also handling frames that are restored to the previous
+    /// contents, see [`Compositor::draw_frame_restoring`]
+    pub fn seek_to_frame_restoring<D>(
+        &mut self,
+        idx: usize,
+        compositor: &mut Compositor,
+        target: &mut D,
+        save: &mut [Rgb565],
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        D: ReadbackTarget<Color = Rgb565>,
+    {
+        self.composite_up_to(idx, compositor, target, |compositor, frame, target| {
+            compositor.draw_frame_restoring(frame, target, save)
+        })
+    }
+
+    pub(crate) fn composite_up_to<D, F>(
+        &mut self,
+        idx: usize,
+        compositor: &mut Compositor,
+        target: &mut D,
+        mut draw: F,
+    ) -> Result<(), CompositeError<D::Error>>
+    where
+        D: DrawTarget<Color = Rgb565>,
+        F: FnMut(
+            &mut Compositor,
+            &mut GifFrame<'_, S, DECODE_BUF, L>,
+            &mut D,
+        ) -> Result<(), CompositeError<D::Error>>,
+    {
+        if idx >= self.num_images() {
+            return Err(CompositeError::Decode(ParseError::NoImagesLeft));
+        }
+        let start = self.key_frame_before(idx);
+        compositor.reset();
+        // A frame restored to the previous contents brings back the screen from before it
+        if !self.header_info.is_opaque_over_screen(start) || self.restores_previous(start) {
+            let screen = Rectangle::new(Point::zero(), self.base_size());
+            target
+                .fill_solid(&screen, compositor.background())
+                .map_err(CompositeError::Draw)?;
+        }
+        for idx in start..=idx {
+            self.seek_to_index(idx).map_err(CompositeError::Decode)?;
+            let mut frame = self.current_frame().map_err(CompositeError::Decode)?;
+            draw(compositor, &mut frame, target)?;
+        }
         Ok(())
     }
 
-    pub fn current_frame(&self) -> Result<GifFrame<'_, S>, ParseError> {
-        let color_table = self
-            .local_image_descriptor
-            .as_ref()
-            .unwrap()
+    /// Frame that was last seeked to, or [`ParseError::NoImagesLeft`] if there is none, e.g. right
+    /// after [`Self::reset`]
+    ///
+    /// Frames without a local color table in a file without a global one have no colors to draw
+    /// with and give [`ParseError::BadGifFile`].
+    pub fn current_frame(&self) -> Result<GifFrame<'_, S, DECODE_BUF, L>, ParseError> {
+        let (idx, image_descriptor) = self
+            .current_index
+            .zip(self.local_image_descriptor.as_ref())
+            .ok_or(ParseError::NoImagesLeft)?;
+        let color_table = image_descriptor
             .color_table()
-            .unwrap_or_else(|| self.header_info.global_table().unwrap());
+            .or_else(|| self.header_info.global_table())
+            .ok_or(ParseError::BadGifFile)?;
+        let frame_area = image_descriptor.bounding_box();
+        let clipped_area = if self.clip_to_screen {
+            frame_area.intersection(&Rectangle::new(Point::zero(), self.base_size()))
+        } else {
+            frame_area
+        };
 
         Ok(GifFrame::new(
             self.bytes.clone(),
             color_table,
-            self.local_image_descriptor.as_ref().unwrap(),
+            image_descriptor,
+            self.header_info.frame_control(idx),
             self.decoder.borrow_mut(),
+            self.fallback_color,
+            clipped_area,
         ))
     }
 
-    pub fn next_frame(&mut self) -> Result<GifFrame<'_, S>, ParseError> {
+    pub fn next_frame(&mut self) -> Result<GifFrame<'_, S, DECODE_BUF, L>, ParseError> {
         self.seek_to_next_frame()?;
-        let color_table = self
-            .local_image_descriptor
-            .as_ref()
-            .unwrap()
-            .color_table()
-            .unwrap_or_else(|| self.header_info.global_table().unwrap());
-
-        Ok(GifFrame::new(
-            self.bytes.clone(),
-            color_table,
-            self.local_image_descriptor.as_ref().unwrap(),
-            self.decoder.borrow_mut(),
-        ))
+        self.current_frame()
     }
 }
 
@@ -176,126 +594,628 @@ pub(crate) enum DecodeState {
     NewSubBlock,
     /// Processing a sublock
     ProcessingSubBlock,
-    /// Block is done
-    BlockDone,
     /// Gif frame is fully processed
     FrameDone,
+    /// Decoding stopped early because of a malformed frame
+    Error(ParseError),
 }
 
-pub struct GifFrame<'header, S: SeekableIter> {
+/// Reads the image data sub-blocks of a frame and feeds them through the LZW decoder
+///
+/// When the source can hand out its bytes as a slice (see [`SeekableIter::as_slice`]), sub-blocks
+/// are fed to the decoder straight from the source instead of being copied into `block_buffer`.
+struct LzwStream<'header, S: SeekableIter, L: LzwDecoder> {
     bytes: ByteIterator<S>,
-    color_table: &'header ColorTable,
-    image_descriptor: &'header LocalImageDescriptor,
-    decoder: RefMut<'header, Decoder>,
+    decoder: RefMut<'header, L>,
     /// Buffer that we write sub-blocks into
     block_buffer: LilQ<255>,
+    /// Whether sub-blocks are read in place from the source
+    zero_copy: bool,
+    /// Bytes of the current sub-block that are left in the source, when reading in place
+    sub_block_remaining: usize,
+    state: DecodeState,
+}
+
+impl<'header, S: SeekableIter, L: LzwDecoder> LzwStream<'header, S, L> {
+    fn done(&self) -> bool {
+        matches!(self.state, DecodeState::FrameDone | DecodeState::Error(_))
+    }
+
+    fn new(bytes: ByteIterator<S>, decoder: RefMut<'header, L>) -> Self {
+        let zero_copy = bytes.remaining_slice().is_some();
+        Self {
+            bytes,
+            decoder,
+            block_buffer: LilQ::new(),
+            zero_copy,
+            sub_block_remaining: 0,
+            state: DecodeState::NewSubBlock,
+        }
+    }
+
+    fn fill_block_buffer(&mut self) -> Result<(), ParseError> {
+        self.block_buffer.reset();
+        self.bytes.seek_by(self.sub_block_remaining)?;
+        self.sub_block_remaining = 0;
+
+        let num_bytes = self.bytes.take_byte()?;
+        if num_bytes == 0 {
+            self.state = DecodeState::FrameDone;
+        } else if self.zero_copy {
+            self.state = DecodeState::ProcessingSubBlock;
+            self.sub_block_remaining = num_bytes as usize;
+        } else {
+            self.state = DecodeState::ProcessingSubBlock;
+            for idx in 0..num_bytes {
+                self.block_buffer.buf[idx as usize] = self.bytes.take_byte()?;
+            }
+            self.block_buffer.size = num_bytes as usize;
+        }
+        Ok(())
+    }
+
+    /// Runs the decoder over the unconsumed part of the current sub-block
+    fn decode_sub_block(&mut self, out: &mut [u8]) -> BufferResult {
+        match self.bytes.remaining_slice() {
+            Some(slice) if self.zero_copy => {
+                let input = &slice[..self.sub_block_remaining.min(slice.len())];
+                let res = self.decoder.decode_bytes(input, out);
+                self.sub_block_remaining -= res.consumed_in;
+                // Can't fail, the decoder consumed at most what was left in the slice
+                let _ = self.bytes.seek_by(res.consumed_in);
+                res
+            }
+            _ => {
+                let res = self
+                    .decoder
+                    .decode_bytes(self.block_buffer.live_slice(), out);
+                self.block_buffer.idx += res.consumed_in;
+                res
+            }
+        }
+    }
+
+    /// Decodes as many indices as fit into `out`, returning how many were written
+    ///
+    /// Returns less than `out.len()` only once the frame is done, or decoding failed.
+    fn decode(&mut self, out: &mut [u8]) -> usize {
+        let mut written = 0;
+        while written < out.len() && !self.done() {
+            if let DecodeState::NewSubBlock = self.state {
+                if let Err(err) = self.fill_block_buffer() {
+                    self.state = DecodeState::Error(err);
+                }
+                continue;
+            }
+
+            let res = self.decode_sub_block(&mut out[written..]);
+            written += res.consumed_out;
+
+            self.state = match res.status {
+                Ok(LzwStatus::Done) => DecodeState::FrameDone,
+                // The decoder may hold on to output until it is called again, so a sub-block is
+                // only finished once it stops making progress
+                Ok(LzwStatus::NoProgress) => DecodeState::NewSubBlock,
+                Ok(LzwStatus::Ok) => DecodeState::ProcessingSubBlock,
+                Err(_) => DecodeState::Error(ParseError::BadLzwData),
+            };
+        }
+        written
+    }
+}
+
+/// A single frame of a GIF, decoded on the fly
+///
+/// Decoding goes through a `DECODE_BUF` byte buffer, see [`GifFrameStreamer`] for the trade-off
+/// its size makes.
+pub struct GifFrame<
+    'header,
+    S: SeekableIter,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L: LzwDecoder = Decoder,
+> {
+    color_table: &'header ColorTable,
+    image_descriptor: &'header LocalImageDescriptor,
+    control: Option<GraphicsControl>,
+    stream: LzwStream<'header, S, L>,
     /// Buffer that we decode the LZW stream into
-    decode_buffer: LilQ<1024>,
-    pub(crate) state: DecodeState,
+    decode_buffer: LilQ<DECODE_BUF>,
+    /// Color used for out of range palette indices; `None` makes them a decode error
+    fallback_color: Option<Rgb565>,
+    /// Part of the frame that is drawn
+    clipped_area: Rectangle,
+    /// Number of pixels yielded so far
+    cursor: usize,
 }
 
-impl<'header, S> GifFrame<'header, S>
+impl<'header, S, const DECODE_BUF: usize, L> GifFrame<'header, S, DECODE_BUF, L>
 where
     S: SeekableIter,
+    L: LzwDecoder,
 {
     pub fn new(
         mut bytes: ByteIterator<S>,
         color_table: &'header ColorTable,
         image_descriptor: &'header LocalImageDescriptor,
-        mut decoder: RefMut<'header, Decoder>,
+        control: Option<GraphicsControl>,
+        mut decoder: RefMut<'header, L>,
+        fallback_color: Option<Rgb565>,
+        clipped_area: Rectangle,
     ) -> Self {
         let code_size = bytes.take_byte().unwrap();
-        decoder.reset(code_size);
+        let reset = decoder.reset(code_size);
+        let mut stream = LzwStream::new(bytes, decoder);
+        if reset.is_err() {
+            stream.state = DecodeState::Error(ParseError::BadLzwData);
+        }
         Self {
-            bytes,
             color_table,
             image_descriptor,
-            //decoder: Decoder::new(weezl::BitOrder::Lsb, code_size),
-            decoder,
+            control,
+            stream,
             decode_buffer: LilQ::new(),
-            block_buffer: LilQ::new(),
-            state: DecodeState::NewSubBlock,
+            fallback_color,
+            clipped_area,
+            cursor: 0,
         }
     }
 
     pub fn done(&self) -> bool {
-        match self.state {
-            DecodeState::FrameDone => true,
-            _ => false,
+        self.stream.done()
+    }
+
+    /// Error that stopped decoding of this frame early, if any
+    pub fn error(&self) -> Option<ParseError> {
+        match self.stream.state {
+            DecodeState::Error(err) => Some(err),
+            _ => None,
         }
     }
 
+    /// Area the frame declares it covers, which may extend past the logical screen
     pub fn frame_area(&self) -> Rectangle {
         self.image_descriptor.bounding_box()
     }
 
-    pub fn img_size(&self) -> Size {
-        self.image_descriptor.size()
+    /// Part of [`Self::frame_area`] that is actually drawn
+    ///
+    /// This is the frame area intersected with the logical screen, unless clipping was disabled
+    /// with [`GifFrameStreamer::set_clip_to_screen`].
+    pub fn clipped_area(&self) -> Rectangle {
+        self.clipped_area
     }
 
-    pub fn origin(&self) -> Point {
-        self.image_descriptor.origin()
+    /// Iterator over the remaining pixels of the frame that fall inside [`Self::clipped_area`]
+    pub fn pixels(&mut self) -> FramePixels<'_, 'header, S, DECODE_BUF, L> {
+        FramePixels {
+            frame: self,
+            limit: usize::MAX,
+            transparent_idx: None,
+        }
     }
 
-    fn fill_block_buffer(&mut self) -> Result<(), ParseError> {
-        self.block_buffer.reset();
+    /// Same as [`Self::pixels`], leaving out pixels that use [`Self::transparent_index`]
+    pub fn opaque_pixels(&mut self) -> FramePixels<'_, 'header, S, DECODE_BUF, L> {
+        FramePixels {
+            transparent_idx: self.transparent_index(),
+            frame: self,
+            limit: usize::MAX,
+        }
+    }
 
-        let num_bytes = self.bytes.take_byte()?;
-        if num_bytes == 0 {
-            self.state = DecodeState::FrameDone;
-        } else {
-            self.state = DecodeState::NewSubBlock;
-            for idx in 0..num_bytes {
-                self.block_buffer.buf[idx as usize] = self.bytes.take_byte()?;
-            }
+    /// Iterator over the remaining pixels of the frame in decode order, with `None` for pixels
+    /// that use [`Self::transparent_index`]
+    ///
+    /// This is what compositing code needs to blend a frame onto its own background, as the
+    /// frame's own iterator yields the palette color of transparent pixels too.
+    pub fn with_alpha(&mut self) -> FrameAlpha<'_, 'header, S, DECODE_BUF, L> {
+        FrameAlpha {
+            transparent_idx: self.transparent_index(),
+            frame: self,
         }
-        self.block_buffer.size = num_bytes as usize;
-        Ok(())
     }
 
-    fn fill_decode_buffer(&mut self) {
-        if !self.done() {
-            self.decode_buffer.reset();
-            while self.decode_buffer.empty() {
-                match self.state {
-                    DecodeState::NewSubBlock => {
-                        self.fill_block_buffer().unwrap();
-                    }
-                    DecodeState::BlockDone => {
-                        self.fill_block_buffer().unwrap();
-                        if self.done() {
-                            return;
-                        }
-                    }
-
-                    _ => {}
-                }
+    /// Same as [`Self::pixels`], converting colors to `C` with ordered dithering
+    ///
+    /// Converting palette colors straight to a lower color depth truncates them, which turns
+    /// gradients into visible bands. Here every pixel is rounded up or down depending on its
+    /// screen position instead, see [`Dither::Ordered`].
+    pub fn dithered_pixels<C>(&mut self) -> DitheredPixels<'_, 'header, C, S, DECODE_BUF, L>
+    where
+        C: RgbColor + From<Rgb888>,
+    {
+        DitheredPixels {
+            frame: self,
+            color: PhantomData,
+        }
+    }
+
+    /// Draws at most the next `max_pixels` pixels of the frame to `target`
+    ///
+    /// This bounds how long a single call can take, so large frames can be drawn across several
+    /// scheduler slices. All decoding state lives in the frame, and the next call picks up exactly
+    /// where the previous one stopped. Pixels outside of [`Self::clipped_area`] count towards
+    /// `max_pixels`, as they still have to be decoded.
+    pub fn draw_step<D>(
+        &mut self,
+        target: &mut D,
+        max_pixels: usize,
+    ) -> Result<DrawProgress, D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let limit = self.cursor.saturating_add(max_pixels);
+        target.draw_iter(FramePixels {
+            frame: self,
+            limit,
+            transparent_idx: None,
+        })?;
 
-                let out = self.decoder.decode_bytes(
-                    self.block_buffer.live_slice(),
-                    &mut self.decode_buffer.buf[..],
-                );
+        let finished = self.cursor >= self.num_pixels()
+            || (self.done() && self.decode_buffer.empty())
+            || !self.visible_pixels_left();
+        Ok(if finished {
+            DrawProgress::Complete
+        } else {
+            DrawProgress::Incomplete
+        })
+    }
 
-                let (consumed_in, consumed_out) = (out.consumed_in, out.consumed_out);
+    /// Whether any pixel that is yet to be decoded falls inside [`Self::clipped_area`]
+    fn visible_pixels_left(&self) -> bool {
+        let clipped_area = self.clipped_area;
+        // Rows are decoded top to bottom, so nothing visible is left past the clipped area
+        let past_bottom = !self.interlaced()
+            && self.pixel_position(self.cursor).y
+                >= clipped_area.top_left.y + clipped_area.size.height as i32;
+        clipped_area.size.height != 0 && !past_bottom
+    }
 
-                match out.status.unwrap() {
-                    LzwStatus::NoProgress | LzwStatus::Done => {
-                        self.state = DecodeState::BlockDone;
-                    }
-                    LzwStatus::Ok => {
-                        self.state = DecodeState::ProcessingSubBlock;
-                    }
+    /// Palette index that the frame's graphics control extension marks as transparent
+    pub fn transparent_index(&self) -> Option<u8> {
+        self.control
+            .as_ref()
+            .and_then(GraphicsControl::get_transparent_idx)
+    }
+
+    /// How the frame's area should be disposed of before the next frame is drawn
+    pub fn disposal_method(&self) -> DisposalMethod {
+        self.control
+            .as_ref()
+            .map(GraphicsControl::disposal_method)
+            .unwrap_or(DisposalMethod::NotSpecified)
+    }
+
+    /// Whether rows are decoded in interlaced order rather than top to bottom
+    pub fn interlaced(&self) -> bool {
+        self.image_descriptor.interlaced()
+    }
+
+    /// Screen position of the `n`th pixel of the frame
+    fn pixel_position(&self, n: usize) -> Point {
+        let width = self.image_descriptor.size().width.max(1) as usize;
+        let row = (n / width) as u32;
+        let row = if self.interlaced() {
+            deinterlace_row(row, self.image_descriptor.size().height)
+        } else {
+            row
+        };
+        self.origin() + Point::new((n % width) as i32, row as i32)
+    }
+
+    /// Decodes the rest of the frame one row at a time
+    ///
+    /// `row` must hold at least [`Self::img_size`]`.width` pixels. Every time a row is complete,
+    /// `f` is called with the screen position of the row's first pixel and its pixels, both
+    /// restricted to [`Self::clipped_area`]. Rows of interlaced frames are reported in the order
+    /// they are decoded, each with its final y coordinate.
+    pub fn decode_rows<F>(&mut self, row: &mut [Rgb565], f: F) -> Result<(), ParseError>
+    where
+        F: FnMut(Point, &[Rgb565]),
+    {
+        self.decode_rows_with(row, Self::next, f)
+    }
+
+    /// Same as [`Self::decode_rows`], but yields raw palette indices instead of colors
+    pub fn decode_index_rows<F>(&mut self, row: &mut [u8], f: F) -> Result<(), ParseError>
+    where
+        F: FnMut(Point, &[u8]),
+    {
+        self.decode_rows_with(row, Self::next_index, f)
+    }
+
+    /// Same as [`Self::decode_rows`], reducing colors to the shades of `C` using `dither`
+    ///
+    /// Each palette entry's luma is computed once up front. Ordered dithering is applied at each
+    /// pixel's screen position, so it needs no memory beyond `row`.
+    pub fn decode_luma_rows<C, F>(
+        &mut self,
+        row: &mut [C],
+        dither: Dither<'_>,
+        f: F,
+    ) -> Result<(), ParseError>
+    where
+        C: LumaColor,
+        F: FnMut(Point, &[C]),
+    {
+        let mut luma_table = [0; 256];
+        for (entry, color) in luma_table.iter_mut().zip(self.palette_rgb()) {
+            *entry = dither::luma(color);
+        }
+        let palette_len = self.palette().len();
+        let width = self.img_size().width as usize;
+        let mut quantizer = LumaQuantizer::new(dither, width)?;
+        if self.cursor == 0 {
+            quantizer.reset();
+        }
+
+        self.decode_rows_with(
+            row,
+            |frame| {
+                let column = frame.cursor % width;
+                let point = frame.pixel_position(frame.cursor);
+                let idx = frame.next_index()?;
+                let luma = if (idx as usize) < palette_len {
+                    luma_table[idx as usize]
+                } else {
+                    dither::luma(frame.color_of(idx)?.into())
+                };
+                Some(quantizer.quantize(point, column, luma))
+            },
+            f,
+        )
+    }
+
+    /// Same as [`Self::decode_index_rows`], with indices mapped to the nearest entries of
+    /// `remap`'s target palette for indexed color displays
+    pub fn decode_remapped_rows<F>(
+        &mut self,
+        row: &mut [u8],
+        remap: &mut PaletteRemap<'_>,
+        f: F,
+    ) -> Result<(), ParseError>
+    where
+        F: FnMut(Point, &[u8]),
+    {
+        let fallback = self.fallback_color.unwrap_or(Rgb565::BLACK).into();
+        let global = self.image_descriptor.color_table().is_none();
+        let table = remap.table(self.palette_rgb(), global, fallback);
+        let palette_len = self.palette().len();
+        self.decode_rows_with(
+            row,
+            |frame| {
+                let idx = frame.next_index()?;
+                if idx as usize >= palette_len {
+                    // Stops decoding if there is no fallback color
+                    frame.color_of(idx)?;
                 }
+                Some(table[idx as usize])
+            },
+            f,
+        )
+    }
+
+    fn decode_rows_with<T, P, F>(
+        &mut self,
+        row: &mut [T],
+        mut next_pixel: P,
+        mut f: F,
+    ) -> Result<(), ParseError>
+    where
+        P: FnMut(&mut Self) -> Option<T>,
+        F: FnMut(Point, &[T]),
+    {
+        let width = self.img_size().width as usize;
+        let row = row.get_mut(..width).ok_or(ParseError::BufferTooSmall)?;
+        if width == 0 {
+            return Ok(());
+        }
 
-                #[cfg(test)]
-                {
-                    println!("consumed out is {}", consumed_out);
+        let clipped_area = self.clipped_area;
+        let first_column = (clipped_area.top_left.x - self.origin().x) as usize;
+        let columns = first_column..first_column + clipped_area.size.width as usize;
+        loop {
+            let row_start = self.cursor - self.cursor % width;
+            for pixel in row[self.cursor - row_start..].iter_mut() {
+                match next_pixel(self) {
+                    Some(val) => *pixel = val,
+                    None => return self.error().map_or(Ok(()), Err),
                 }
+            }
+
+            let start = Point::new(clipped_area.top_left.x, self.pixel_position(row_start).y);
+            if clipped_area.contains(start) {
+                f(start, &row[columns.clone()]);
+            }
+        }
+    }
+
+    /// Iterator over the remaining raw palette indices of the frame, in decode order
+    pub fn indices(&mut self) -> FrameIndices<'_, 'header, S, DECODE_BUF, L> {
+        FrameIndices { frame: self }
+    }
+
+    /// Decodes the rest of the frame into `buf` as palette indices, in row-major order
+    ///
+    /// `buf` must hold at least [`Self::num_pixels`] indices. Unlike the iterator APIs this skips
+    /// any per-pixel bookkeeping: non-interlaced frames are decoded straight into `buf` and
+    /// interlaced ones are copied into place a row at a time. Decoding the frame once and then
+    /// rendering it from `buf` avoids re-running LZW for every redraw.
+    ///
+    /// If the LZW stream ends before the frame is complete, the indices it is missing are set to 0
+    /// rather than leaving whatever `buf` held before in their place.
+    pub fn decode_indices(&mut self, buf: &mut [u8]) -> Result<(), ParseError> {
+        let num_pixels = self.num_pixels();
+        let buf = buf
+            .get_mut(..num_pixels)
+            .ok_or(ParseError::BufferTooSmall)?;
+        let Size { width, height } = self.img_size();
+        let width = width as usize;
 
-                self.decode_buffer.size = consumed_out;
-                self.block_buffer.idx += consumed_in;
+        if self.interlaced() {
+            let result = self.for_each_run(|row, column, run| {
+                buf[row * width + column..][..run.len()].copy_from_slice(run)
+            });
+            // Rows the stream ended before, in the order they are stored
+            let mut start = self.cursor;
+            while start < num_pixels {
+                let (row, column) = (start / width, start % width);
+                let row = deinterlace_row(row as u32, height) as usize;
+                buf[row * width + column..][..width - column].fill(0);
+                start += width - column;
             }
+            return result;
+        }
+
+        // Hand out whatever the iterator APIs left behind before bypassing the decode buffer
+        let pending = self.decode_buffer.live_slice();
+        let len = pending.len().min(num_pixels - self.cursor);
+        buf[self.cursor..][..len].copy_from_slice(&pending[..len]);
+        self.cursor += len;
+        self.decode_buffer.reset();
+
+        self.cursor += self.stream.decode(&mut buf[self.cursor..]);
+        buf[self.cursor..].fill(0);
+        self.error().map_or(Ok(()), Err)
+    }
+
+    /// Decodes the rest of the frame into a buffer of palette indices covering the logical screen
+    ///
+    /// `buf` holds rows of `stride` indices. The frame is written at its origin, restricted to
+    /// [`Self::clipped_area`]; everything else in `buf` is left untouched.
+    pub fn decode_indices_to_screen(
+        &mut self,
+        buf: &mut [u8],
+        stride: usize,
+    ) -> Result<(), ParseError> {
+        let area = self.clipped_area;
+        if let Some(bottom_right) = area.bottom_right() {
+            let (x, y) = (bottom_right.x as usize, bottom_right.y as usize);
+            if x >= stride || y * stride + x >= buf.len() {
+                return Err(ParseError::BufferTooSmall);
+            }
+        }
+
+        let origin = self.origin();
+        let first_column = (area.top_left.x - origin.x) as usize;
+        let columns = first_column..first_column + area.size.width as usize;
+        let first_row = (area.top_left.y - origin.y) as usize;
+        let rows = first_row..first_row + area.size.height as usize;
+        self.for_each_run(|row, column, run| {
+            let start = column.max(columns.start);
+            let end = (column + run.len()).min(columns.end);
+            if !rows.contains(&row) || start >= end {
+                return;
+            }
+            let offset = (origin.y as usize + row) * stride + origin.x as usize + start;
+            buf[offset..][..end - start].copy_from_slice(&run[start - column..end - column]);
+        })
+    }
+
+    /// Hands the rest of the frame's indices to `f` in runs that never cross a row, along with
+    /// the frame relative row and column of the run's first index
+    fn for_each_run<F>(&mut self, mut f: F) -> Result<(), ParseError>
+    where
+        F: FnMut(usize, usize, &[u8]),
+    {
+        let num_pixels = self.num_pixels();
+        let Size { width, height } = self.img_size();
+        while self.cursor < num_pixels {
+            if self.decode_buffer.empty() {
+                self.fill_decode_buffer();
+                if self.decode_buffer.empty() {
+                    break;
+                }
+            }
+
+            let column = self.cursor % width as usize;
+            let row = (self.cursor / width as usize) as u32;
+            let row = if self.interlaced() {
+                deinterlace_row(row, height)
+            } else {
+                row
+            };
+            let pending = self.decode_buffer.live_slice();
+            let len = pending
+                .len()
+                .min(width as usize - column)
+                .min(num_pixels - self.cursor);
+            f(row as usize, column, &pending[..len]);
+            self.decode_buffer.idx += len;
+            self.cursor += len;
         }
+        self.error().map_or(Ok(()), Err)
+    }
+
+    /// Decodes the rest of the frame into `buf` with [`Self::decode_indices`], keeping what is
+    /// needed to draw it again later without decoding
+    pub fn cache_indices<'buf>(
+        &mut self,
+        buf: &'buf mut [u8],
+    ) -> Result<CachedFrame<'buf>, ParseError> {
+        self.decode_indices(buf)?;
+        Ok(CachedFrame::new(
+            &buf[..self.num_pixels()],
+            self.frame_area(),
+            self.clipped_area,
+            self.transparent_index(),
+        ))
+    }
+
+    /// Palette that the frame's indices refer to; either its local color table or the global one
+    ///
+    /// Colors are given as they are drawn, after the palette transform.
+    pub fn palette(&self) -> &'header [Rgb565] {
+        &self.color_table.table
+    }
+
+    /// Same as [`Self::palette`], as the 24 bit RGB triples stored in the file after the palette
+    /// transform
+    pub fn palette_rgb(&self) -> impl ExactSizeIterator<Item = Rgb888> + Clone + 'header {
+        self.color_table.rgb()
+    }
+
+    /// Decodes the next palette index of the frame
+    fn next_index(&mut self) -> Option<u8> {
+        if self.cursor >= self.num_pixels() {
+            return None;
+        }
+        if self.decode_buffer.empty() {
+            self.fill_decode_buffer()
+        }
+        let idx = self.decode_buffer.next()?;
+        self.cursor += 1;
+        Some(idx)
+    }
+
+    /// Looks `idx` up in the color table, stopping decoding if it is out of range and there is
+    /// no fallback color
+    fn color_of(&mut self, idx: u8) -> Option<Rgb565> {
+        let color = self.color_table.get(idx).or(self.fallback_color);
+        if color.is_none() {
+            self.decode_buffer.reset();
+            self.stream.state = DecodeState::Error(ParseError::PaletteIndexOutOfRange);
+        }
+        color
+    }
+
+    /// Same as [`Self::color_of`], before conversion to [`Rgb565`]
+    fn rgb_of(&mut self, idx: u8) -> Option<Rgb888> {
+        let color = self.color_table.get_rgb(idx);
+        color.or_else(|| self.color_of(idx).map(Rgb888::from))
+    }
+
+    pub fn img_size(&self) -> Size {
+        self.image_descriptor.size()
+    }
+
+    pub fn origin(&self) -> Point {
+        self.image_descriptor.origin()
+    }
+
+    fn fill_decode_buffer(&mut self) {
+        self.decode_buffer.reset();
+        self.decode_buffer.size = self.stream.decode(&mut self.decode_buffer.buf);
     }
 
     pub fn num_pixels(&self) -> usize {
@@ -303,15 +1223,162 @@ where
     }
 }
 
-impl<S: SeekableIter> Iterator for GifFrame<'_, S> {
+impl<S: SeekableIter, const DECODE_BUF: usize, L: LzwDecoder> Iterator
+    for GifFrame<'_, S, DECODE_BUF, L>
+{
     ///TODO: Suppport other colors
     type Item = Rgb565;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.decode_buffer.empty() {
-            self.fill_decode_buffer()
+        let idx = self.next_index()?;
+        self.color_of(idx)
+    }
+}
+
+/// Raw palette indices of a [`GifFrame`], see [`GifFrame::palette`] for the colors they refer to
+pub struct FrameIndices<
+    'frame,
+    'header,
+    S: SeekableIter,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L: LzwDecoder = Decoder,
+> {
+    frame: &'frame mut GifFrame<'header, S, DECODE_BUF, L>,
+}
+
+impl<S: SeekableIter, const DECODE_BUF: usize, L: LzwDecoder> Iterator
+    for FrameIndices<'_, '_, S, DECODE_BUF, L>
+{
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frame.next_index()
+    }
+}
+
+/// Colors of a [`GifFrame`] that are `None` where the frame is transparent
+pub struct FrameAlpha<
+    'frame,
+    'header,
+    S: SeekableIter,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L: LzwDecoder = Decoder,
+> {
+    frame: &'frame mut GifFrame<'header, S, DECODE_BUF, L>,
+    transparent_idx: Option<u8>,
+}
+
+impl<S: SeekableIter, const DECODE_BUF: usize, L: LzwDecoder> Iterator
+    for FrameAlpha<'_, '_, S, DECODE_BUF, L>
+{
+    type Item = Option<Rgb565>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.frame.next_index()?;
+        if Some(idx) == self.transparent_idx {
+            return Some(None);
+        }
+        self.frame.color_of(idx).map(Some)
+    }
+}
+
+/// Progress made by [`GifFrame::draw_step`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawProgress {
+    /// Part of the frame is still left to draw
+    Incomplete,
+    /// Nothing is left to draw, either because the frame is fully drawn or decoding stopped
+    /// early; see [`GifFrame::error`]
+    Complete,
+}
+
+/// Maps the `row`th decoded row of an interlaced frame with `height` rows to its y offset
+///
+/// Interlaced frames store every 8th row starting at 0, then every 8th row starting at 4, then
+/// every 4th row starting at 2 and finally every odd row.
+fn deinterlace_row(row: u32, height: u32) -> u32 {
+    let pass_1 = (height + 7) / 8;
+    let pass_2 = (height + 3) / 8;
+    let pass_3 = (height + 1) / 4;
+    if row < pass_1 {
+        row * 8
+    } else if row < pass_1 + pass_2 {
+        (row - pass_1) * 8 + 4
+    } else if row < pass_1 + pass_2 + pass_3 {
+        (row - pass_1 - pass_2) * 4 + 2
+    } else {
+        (row - pass_1 - pass_2 - pass_3) * 2 + 1
+    }
+}
+
+/// Positioned pixels of a [`GifFrame`], restricted to its [`GifFrame::clipped_area`]
+pub struct FramePixels<
+    'frame,
+    'header,
+    S: SeekableIter,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L: LzwDecoder = Decoder,
+> {
+    frame: &'frame mut GifFrame<'header, S, DECODE_BUF, L>,
+    /// Value of the frame's cursor to stop at
+    limit: usize,
+    /// Palette index of pixels that are skipped
+    transparent_idx: Option<u8>,
+}
+
+impl<S: SeekableIter, const DECODE_BUF: usize, L: LzwDecoder> Iterator
+    for FramePixels<'_, '_, S, DECODE_BUF, L>
+{
+    type Item = Pixel<Rgb565>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let clipped_area = self.frame.clipped_area;
+        loop {
+            if self.frame.cursor >= self.limit || !self.frame.visible_pixels_left() {
+                return None;
+            }
+            let point = self.frame.pixel_position(self.frame.cursor);
+            let idx = self.frame.next_index()?;
+            if Some(idx) == self.transparent_idx {
+                continue;
+            }
+            let color = self.frame.color_of(idx)?;
+            if clipped_area.contains(point) {
+                return Some(Pixel(point, color));
+            }
+        }
+    }
+}
+
+/// Positioned pixels of a [`GifFrame`] dithered to `C`, see [`GifFrame::dithered_pixels`]
+pub struct DitheredPixels<
+    'frame,
+    'header,
+    C,
+    S: SeekableIter,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L: LzwDecoder = Decoder,
+> {
+    frame: &'frame mut GifFrame<'header, S, DECODE_BUF, L>,
+    color: PhantomData<C>,
+}
+
+impl<C, S, const DECODE_BUF: usize, L> Iterator for DitheredPixels<'_, '_, C, S, DECODE_BUF, L>
+where
+    C: RgbColor + From<Rgb888>,
+    S: SeekableIter,
+    L: LzwDecoder,
+{
+    type Item = Pixel<C>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let clipped_area = self.frame.clipped_area;
+        loop {
+            if !self.frame.visible_pixels_left() {
+                return None;
+            }
+            let point = self.frame.pixel_position(self.frame.cursor);
+            let idx = self.frame.next_index()?;
+            let color = self.frame.rgb_of(idx)?;
+            if clipped_area.contains(point) {
+                return Some(Pixel(point, dither::ordered_rgb(color, point)));
+            }
         }
-        let val = self.decode_buffer.next();
-        val.map(|idx| self.color_table.table[idx as usize])
     }
 }
 
@@ -321,7 +1388,10 @@ mod tests {
     use embedded_graphics::{image::Image, prelude::*};
 
     use super::*;
-    use crate::test_utils::Framebuffer;
+    use crate::{
+        palette::BorrowedPalettes,
+        test_utils::{encode_gif, CopyingIter, Framebuffer, TestFrame, PALETTE},
+    };
     use std::{io::Cursor, vec::Vec};
     //
     fn iterate_gif(bytes: &[u8]) {
@@ -379,4 +1449,770 @@ mod tests {
             Image::new(&gif, Point::zero()).draw(&mut fb).unwrap();
         }
     }
+
+    #[test]
+    fn reset_after_seek() {
+        let bee = include_bytes!("test/bee.gif");
+        let mut gif = GifFrameStreamer::from_slice(bee).unwrap();
+        let mut fb = Framebuffer::<240, 240>::new();
+
+        gif.seek_to_next_frame().unwrap();
+        gif.reset().unwrap();
+        assert_eq!(gif.frame_index(), None);
+        assert!(matches!(gif.current_frame(), Err(ParseError::NoImagesLeft)));
+        Image::new(&gif, Point::zero()).draw(&mut fb).unwrap();
+
+        let first = gif.next_frame().unwrap().count();
+        gif.reset().unwrap();
+        assert_eq!(gif.next_frame().unwrap().count(), first);
+    }
+
+    #[test]
+    fn out_of_range_index_uses_fallback() {
+        let gif = encode_gif(
+            (4, 1),
+            &PALETTE,
+            &[TestFrame::new((4, 1), vec![1, 200, 2, 3])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        frames.set_fallback_color(Some(Rgb565::WHITE));
+
+        let frame = frames.next_frame().unwrap();
+        let pixels: Vec<Rgb565> = frame.collect();
+        assert_eq!(
+            pixels,
+            [Rgb565::RED, Rgb565::WHITE, Rgb565::GREEN, Rgb565::BLUE]
+        );
+    }
+
+    #[test]
+    fn out_of_range_index_is_error() {
+        let gif = encode_gif(
+            (4, 1),
+            &PALETTE,
+            &[TestFrame::new((4, 1), vec![1, 200, 2, 3])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        frames.set_fallback_color(None);
+
+        let mut frame = frames.next_frame().unwrap();
+        assert_eq!(frame.next(), Some(Rgb565::RED));
+        assert_eq!(frame.next(), None);
+        assert_eq!(frame.next(), None);
+        assert_eq!(frame.error(), Some(ParseError::PaletteIndexOutOfRange));
+    }
+
+    #[test]
+    fn frames_are_clipped_to_screen() {
+        let mut frame = TestFrame::new((4, 2), vec![1, 1, 2, 2, 3, 3, 1, 1]);
+        frame.origin = (2, 0);
+        let gif = encode_gif((4, 2), &PALETTE, &[frame]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        frames.seek_to_next_frame().unwrap();
+        let mut frame = frames.current_frame().unwrap();
+        assert_eq!(
+            frame.frame_area(),
+            Rectangle::new(Point::new(2, 0), Size::new(4, 2))
+        );
+        assert_eq!(
+            frame.clipped_area(),
+            Rectangle::new(Point::new(2, 0), Size::new(2, 2))
+        );
+        let pixels: Vec<Pixel<Rgb565>> = frame.pixels().collect();
+        assert_eq!(
+            pixels,
+            [
+                Pixel(Point::new(2, 0), Rgb565::RED),
+                Pixel(Point::new(3, 0), Rgb565::RED),
+                Pixel(Point::new(2, 1), Rgb565::BLUE),
+                Pixel(Point::new(3, 1), Rgb565::BLUE),
+            ]
+        );
+        drop(frame);
+
+        let mut fb = Framebuffer::<4, 2>::new();
+        Image::new(&frames, Point::zero()).draw(&mut fb).unwrap();
+        assert_eq!(fb.pixel(Point::new(3, 1)), Rgb565::BLUE);
+        assert_eq!(fb.pixel(Point::new(1, 1)), Rgb565::BLACK);
+
+        frames.set_clip_to_screen(false);
+        let mut frame = frames.current_frame().unwrap();
+        assert_eq!(frame.clipped_area(), frame.frame_area());
+        assert_eq!(frame.pixels().count(), 8);
+    }
+
+    #[test]
+    fn deinterlace_visits_every_row() {
+        for height in 0..40 {
+            let mut rows: Vec<u32> = (0..height)
+                .map(|row| deinterlace_row(row, height))
+                .collect();
+            rows.sort();
+            assert_eq!(rows, (0..height).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn decode_interlaced_rows() {
+        let indices: Vec<u8> = (0..20).map(|idx| (idx / 2) % 4).collect();
+        let mut frame = TestFrame::new((2, 10), indices);
+        frame.interlaced = true;
+        let gif = encode_gif((2, 10), &PALETTE, &[frame]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        assert!(frame.interlaced());
+        let mut row = [Rgb565::BLACK; 2];
+        let mut rows = Vec::new();
+        frame
+            .decode_rows(&mut row, |start, pixels| {
+                rows.push((start.y, pixels.to_vec()))
+            })
+            .unwrap();
+
+        let order: Vec<i32> = rows.iter().map(|(y, _)| *y).collect();
+        assert_eq!(order, [0, 8, 4, 2, 6, 1, 3, 5, 7, 9]);
+        for (y, pixels) in rows {
+            let [r, g, b] = PALETTE[y as usize % 4];
+            let color = Rgb565::from(Rgb888::new(r, g, b));
+            assert_eq!(pixels, [color, color]);
+        }
+    }
+
+    #[test]
+    fn decode_rows_needs_full_row() {
+        let gif = encode_gif(
+            (4, 1),
+            &PALETTE,
+            &[TestFrame::new((4, 1), vec![1, 2, 3, 0])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        let mut row = [Rgb565::BLACK; 3];
+        let res = frames
+            .next_frame()
+            .unwrap()
+            .decode_rows(&mut row, |_, _| {});
+        assert_eq!(res, Err(ParseError::BufferTooSmall));
+    }
+
+    #[test]
+    fn footprint() {
+        use core::mem::size_of;
+
+        // Keeps the numbers in the docs of `GifFrameStreamer` honest
+        let bookkeeping = 4 * size_of::<usize>();
+        assert!(size_of::<ColorTable>() <= 768 + 512 + bookkeeping);
+        assert!(size_of::<GifFrameStreamer<SeekableSliceIter<'_>>>() <= 17 * 1024);
+        assert!(
+            size_of::<GifFrame<'_, SeekableSliceIter<'_>>>() <= DEFAULT_DECODE_BUFFER_SIZE + 450
+        );
+        assert!(size_of::<GifFrame<'_, SeekableSliceIter<'_>, 64>>() <= 64 + 450);
+    }
+
+    #[test]
+    fn missing_color_table() {
+        let mut local = TestFrame::new((1, 1), vec![1]);
+        local.local_table = Some(PALETTE.to_vec());
+        let gif = encode_gif((1, 1), &[], &[local, TestFrame::new((1, 1), vec![1])]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        assert_eq!(frames.next_frame().unwrap().next(), Some(Rgb565::RED));
+        // Without a table of its own, the second frame has no colors at all
+        assert!(matches!(frames.next_frame(), Err(ParseError::BadGifFile)));
+    }
+
+    #[test]
+    fn raw_indices_and_palette() {
+        // Colors whose low bits don't survive a trip through Rgb565
+        let colors = [[100, 50, 25], [255, 0, 0], [0, 255, 0], [3, 7, 250]];
+        let indices = vec![0, 3, 200, 1, 2, 2];
+        let gif = encode_gif((3, 2), &colors, &[TestFrame::new((3, 2), indices.clone())]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        frames.seek_to_next_frame().unwrap();
+        let mut frame = frames.current_frame().unwrap();
+        let palette: Vec<Rgb888> = colors
+            .iter()
+            .map(|&[r, g, b]| Rgb888::new(r, g, b))
+            .collect();
+        assert_eq!(frame.palette_rgb().collect::<Vec<Rgb888>>(), palette);
+        let drawn: Vec<Rgb565> = palette.iter().map(|&color| color.into()).collect();
+        assert_eq!(frame.palette(), &drawn[..]);
+        assert_eq!(frame.indices().collect::<Vec<u8>>(), indices);
+        drop(frame);
+
+        let mut frame = frames.current_frame().unwrap();
+        let mut row = [0; 3];
+        let mut rows = Vec::new();
+        frame
+            .decode_index_rows(&mut row, |start, indices| {
+                rows.push((start, indices.to_vec()))
+            })
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (Point::new(0, 0), vec![0, 3, 200]),
+                (Point::new(0, 1), vec![1, 2, 2])
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_code_size() {
+        let mut gif = encode_gif(
+            (1, 1),
+            &[[0, 0, 0], [255, 255, 255]],
+            &[TestFrame::new((1, 1), vec![0])],
+        );
+        let frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        // The minimum code size follows the image descriptor
+        let code_size_offset = frames.header_info.frame_offset(0).unwrap() + 9;
+        gif[code_size_offset] = 12;
+
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        let mut frame = frames.next_frame().unwrap();
+        assert_eq!(frame.next(), None);
+        assert_eq!(frame.error(), Some(ParseError::BadLzwData));
+    }
+
+    #[test]
+    fn transparent_pixels_have_no_color() {
+        let mut frame = TestFrame::new((2, 2), vec![0, 1, 2, 1]);
+        frame.transparent = Some(1);
+        let gif = encode_gif((2, 2), &PALETTE, &[frame]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        let colors: Vec<Option<Rgb565>> = frame.with_alpha().collect();
+        assert_eq!(
+            colors,
+            [
+                Some(Rgb565::from(Rgb888::new(0, 0, 0))),
+                None,
+                Some(Rgb565::from(Rgb888::new(0, 255, 0))),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_luma_rows() {
+        use embedded_graphics::pixelcolor::{BinaryColor, Gray2};
+
+        let gif = encode_gif(
+            (4, 2),
+            &PALETTE,
+            &[TestFrame::new((4, 2), vec![0, 1, 2, 3, 3, 2, 1, 0])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        let mut row = [Gray2::BLACK; 4];
+        let mut rows = Vec::new();
+        frame
+            .decode_luma_rows(&mut row, Dither::Threshold(128), |start, row| {
+                rows.push((start, row.to_vec()))
+            })
+            .unwrap();
+        // Red, green and blue have a luma of 76, 149 and 28
+        let shades = |shades: [u8; 4]| shades.map(Gray2::new).to_vec();
+        assert_eq!(
+            rows,
+            [
+                (Point::new(0, 0), shades([0, 1, 2, 0])),
+                (Point::new(0, 1), shades([0, 2, 1, 0]))
+            ]
+        );
+        drop(frame);
+
+        let mut frame = frames.next_frame().unwrap();
+        let mut row = [BinaryColor::Off; 3];
+        let mut errors = [0; 11];
+        let result =
+            frame.decode_luma_rows(&mut row, Dither::ErrorDiffusion(&mut errors), |_, _| {});
+        assert_eq!(result, Err(ParseError::BufferTooSmall));
+    }
+
+    #[test]
+    fn luma_of_file_colors() {
+        use embedded_graphics::pixelcolor::Gray8;
+
+        // Colors whose low bits don't survive a trip through Rgb565
+        let gif = encode_gif(
+            (2, 1),
+            &[[100, 50, 25], [3, 7, 250]],
+            &[TestFrame::new((2, 1), vec![0, 1])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        let mut row = [Gray8::BLACK; 2];
+        let mut shades = Vec::new();
+        frame
+            .decode_luma_rows(&mut row, Dither::Threshold(128), |_, row| {
+                shades.extend_from_slice(row)
+            })
+            .unwrap();
+        assert_eq!(shades, [Gray8::new(62), Gray8::new(33)]);
+    }
+
+    #[test]
+    fn dithered_pixels() {
+        // Red sits a bit over halfway between the two darkest levels of a 5 bit channel
+        let gif = encode_gif(
+            (8, 8),
+            &[[4, 0, 0], [0, 0, 0]],
+            &[TestFrame::new((8, 8), vec![0; 64])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        let colors: Vec<Rgb565> = frame.dithered_pixels().map(|pixel| pixel.1).collect();
+        assert_eq!(colors.len(), 64);
+        assert!(colors.iter().all(|color| color.r() <= 1 && color.g() == 0));
+        // Truncating would turn every pixel black
+        assert_eq!(colors.iter().filter(|color| color.r() == 1).count(), 31);
+    }
+
+    #[test]
+    fn palette_transform() {
+        use crate::palette::Brightness;
+
+        let mut local = TestFrame::new((2, 1), vec![0, 1]);
+        local.local_table = Some(vec![[200, 100, 50], [255, 255, 255]]);
+        let gif = encode_gif(
+            (2, 1),
+            &PALETTE,
+            &[TestFrame::new((2, 1), vec![1, 2]), local],
+        );
+        let frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        let mut frames = frames.with_palette_transform(Brightness(128)).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        assert_eq!(frame.palette_rgb().nth(1), Some(Rgb888::new(128, 0, 0)));
+        assert_eq!(frame.next(), Some(Rgb565::from(Rgb888::new(128, 0, 0))));
+        drop(frame);
+
+        frames.seek_to_next_frame().unwrap();
+        let palette: Vec<Rgb888> = frames.current_frame().unwrap().palette_rgb().collect();
+        assert_eq!(
+            palette,
+            [Rgb888::new(100, 50, 25), Rgb888::new(128, 128, 128)]
+        );
+        // Both the loaded local table and the global one are re-read
+        frames.set_palette_transform(Brightness(255)).unwrap();
+        let palette: Vec<Rgb888> = frames.current_frame().unwrap().palette_rgb().collect();
+        assert_eq!(palette, [Rgb888::new(200, 100, 50), Rgb888::WHITE]);
+        let mut frame = frames.next_frame().unwrap();
+        assert_eq!(frame.palette_rgb().nth(1), Some(Rgb888::RED));
+        assert_eq!(frame.next(), Some(Rgb565::RED));
+        drop(frame);
+
+        // Only the global table is re-read while no frame is loaded
+        frames.reset().unwrap();
+        frames.set_palette_transform(Brightness(128)).unwrap();
+        let mut frame = frames.next_frame().unwrap();
+        assert_eq!(frame.next(), Some(Rgb565::from(Rgb888::new(128, 0, 0))));
+    }
+
+    #[test]
+    fn remapped_rows() {
+        use crate::palette::ColorDistance;
+
+        let mut local = TestFrame::new((3, 1), vec![0, 1, 2]);
+        local.local_table = Some(vec![[250, 250, 250], [10, 10, 10], [0, 0, 0], [0, 0, 0]]);
+        let gif = encode_gif(
+            (3, 1),
+            &PALETTE,
+            &[TestFrame::new((3, 1), vec![1, 3, 9]), local],
+        );
+        let theme = [Rgb888::WHITE, Rgb888::new(0, 0, 250)];
+        let mut frames = GifFrameStreamer::from_slice(&gif)
+            .unwrap()
+            .with_palette_overrides(BorrowedPalettes::default())
+            .unwrap();
+        let target = [
+            Rgb888::BLACK,
+            Rgb888::WHITE,
+            Rgb888::new(0, 0, 200),
+            Rgb888::new(200, 0, 0),
+        ];
+        let mut remap = PaletteRemap::new(&target, ColorDistance::Rgb);
+
+        type Streamer<'a> = GifFrameStreamer<
+            SeekableSliceIter<'a>,
+            DEFAULT_DECODE_BUFFER_SIZE,
+            Decoder,
+            NoTransform,
+            BorrowedPalettes<'a>,
+        >;
+        let mut decode = |frames: &mut Streamer| {
+            let mut frame = frames.next_frame().unwrap();
+            let mut row = [0; 3];
+            let mut rows = Vec::new();
+            frame
+                .decode_remapped_rows(&mut row, &mut remap, |_, row| rows.push(row.to_vec()))
+                .unwrap();
+            rows
+        };
+        // The out of range index gets the black fallback color
+        assert_eq!(decode(&mut frames), [[3, 2, 0]]);
+        assert_eq!(decode(&mut frames), [[1, 0, 0]]);
+
+        // Changing the global palette or fallback color doesn't leave a stale mapping behind
+        let themed = BorrowedPalettes {
+            global: Some(&theme),
+            frames: &[],
+        };
+        frames.set_palette_overrides(themed).unwrap();
+        frames.set_fallback_color(Some(Rgb565::WHITE));
+        assert_eq!(decode(&mut frames), [[2, 1, 1]]);
+        frames
+            .set_palette_overrides(BorrowedPalettes::default())
+            .unwrap();
+        frames.next_frame().unwrap();
+        assert_eq!(decode(&mut frames), [[3, 2, 1]]);
+    }
+
+    #[test]
+    fn palette_overrides() {
+        let mut local = TestFrame::new((1, 1), vec![1]);
+        local.local_table = Some(vec![[1, 2, 3], [4, 5, 6]]);
+        let gif = encode_gif((1, 1), &PALETTE, &[TestFrame::new((1, 1), vec![1]), local]);
+        let theme = [Rgb888::WHITE, Rgb888::CYAN];
+        let frame_theme = [Rgb888::BLACK, Rgb888::YELLOW];
+        let frame_themes = [(0, &frame_theme[..]), (1, &frame_theme[..])];
+        let frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        assert_eq!(core::mem::size_of_val(frames.palette_overrides()), 0);
+
+        let mut frames = frames
+            .with_palette_overrides(BorrowedPalettes {
+                global: Some(&theme),
+                frames: &frame_themes[1..],
+            })
+            .unwrap();
+        frames.seek_to_next_frame().unwrap();
+        let palette: Vec<Rgb888> = frames.current_frame().unwrap().palette_rgb().collect();
+        assert_eq!(palette, theme);
+        let palette: Vec<Rgb888> = frames.next_frame().unwrap().palette_rgb().collect();
+        assert_eq!(palette, frame_theme);
+
+        // Overrides survive a change of palette transform
+        let mut frames = frames
+            .with_palette_transform(|color: Rgb888| Rgb888::new(color.b(), color.g(), color.r()))
+            .unwrap();
+        assert_eq!(frames.current_frame().unwrap().next(), Some(Rgb565::CYAN));
+
+        // The loaded frame goes back to its own table, others can be overridden as well
+        frames
+            .set_palette_overrides(BorrowedPalettes {
+                global: None,
+                frames: &frame_themes[..1],
+            })
+            .unwrap();
+        assert_eq!(
+            frames.current_frame().unwrap().palette_rgb().nth(1),
+            Some(Rgb888::new(6, 5, 4))
+        );
+        assert_eq!(frames.next_frame().unwrap().next(), Some(Rgb565::CYAN));
+
+        frames
+            .set_palette_overrides(BorrowedPalettes::default())
+            .unwrap();
+        assert_eq!(frames.current_frame().unwrap().next(), Some(Rgb565::BLUE));
+    }
+
+    #[test]
+    fn seek_to_frame() {
+        let mut cleared = TestFrame::new((2, 2), vec![3; 4]);
+        cleared.disposal = 2;
+        let mut corner = TestFrame::new((1, 1), vec![1]);
+        corner.origin = (1, 1);
+        let mut overlay = TestFrame::new((2, 2), vec![0, 2, 0, 0]);
+        overlay.transparent = Some(0);
+        let gif = encode_gif(
+            (2, 2),
+            &[[0, 0, 0], [255, 0, 0], [0, 255, 0], [0, 0, 255]],
+            &[
+                TestFrame::new((2, 2), vec![1; 4]),
+                TestFrame::new((1, 1), vec![2]),
+                cleared,
+                corner,
+                overlay,
+            ],
+        );
+        let screen = |fb: &Framebuffer<2, 2>| -> Vec<Rgb565> {
+            Rectangle::new(Point::zero(), Size::new(2, 2))
+                .points()
+                .map(|point| fb.pixel(point))
+                .collect()
+        };
+
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut fb = Framebuffer::<2, 2>::new();
+        let expected: Vec<Vec<Rgb565>> = (0..frames.num_images())
+            .map(|_| {
+                compositor
+                    .draw_frame(&mut frames.next_frame().unwrap(), &mut fb)
+                    .unwrap();
+                screen(&fb)
+            })
+            .collect();
+
+        for idx in (0..frames.num_images()).rev() {
+            let mut fb = Framebuffer::<2, 2>::new();
+            fb.clear(Rgb565::YELLOW).unwrap();
+            let mut compositor = Compositor::new(Rgb565::WHITE);
+            frames.seek_to_frame(idx, &mut compositor, &mut fb).unwrap();
+            assert_eq!(frames.frame_index(), Some(idx));
+            assert_eq!(screen(&fb), expected[idx], "frame {}", idx);
+        }
+        // Starting over after the full screen clear
+        assert_eq!(frames.key_frame_before(4), 3);
+        assert_eq!(frames.key_frame_before(1), 0);
+
+        let result = frames.seek_to_frame(5, &mut compositor, &mut fb);
+        assert_eq!(
+            result,
+            Err(CompositeError::Decode(ParseError::NoImagesLeft))
+        );
+
+        // Restoring the green frame needs the red screen from before it
+        let mut restored = TestFrame::new((2, 1), vec![2; 2]);
+        restored.disposal = 3;
+        let gif = encode_gif(
+            (2, 1),
+            &PALETTE,
+            &[
+                TestFrame::new((2, 1), vec![1; 2]),
+                restored,
+                TestFrame::new((1, 1), vec![3]),
+            ],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        assert!(!frames.is_key_frame(1));
+        assert_eq!(frames.key_frame_before(2), 0);
+        let mut fb = Framebuffer::<2, 1>::new();
+        fb.clear(Rgb565::YELLOW).unwrap();
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut save = vec![Rgb565::BLACK; frames.restore_buffer_len()];
+        frames
+            .seek_to_frame_restoring(2, &mut compositor, &mut fb, &mut save)
+            .unwrap();
+        assert_eq!(
+            [fb.pixel(Point::zero()), fb.pixel(Point::new(1, 0))],
+            [Rgb565::BLUE, Rgb565::RED]
+        );
+
+        // Restoring a first frame that covers the screen brings back the background
+        let mut restored = TestFrame::new((2, 1), vec![2; 2]);
+        restored.disposal = 3;
+        let gif = encode_gif(
+            (2, 1),
+            &PALETTE,
+            &[restored, TestFrame::new((1, 1), vec![3])],
+        );
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        fb.clear(Rgb565::YELLOW).unwrap();
+        frames
+            .seek_to_frame_restoring(1, &mut compositor, &mut fb, &mut save)
+            .unwrap();
+        assert_eq!(
+            [fb.pixel(Point::zero()), fb.pixel(Point::new(1, 0))],
+            [Rgb565::BLUE, Rgb565::WHITE]
+        );
+    }
+
+    #[test]
+    fn dirty_area() {
+        let mut cleared = TestFrame::new((1, 1), vec![1]);
+        cleared.origin = (2, 0);
+        cleared.disposal = 2;
+        let mut corner = TestFrame::new((2, 1), vec![1; 2]);
+        corner.origin = (0, 2);
+        let mut outside = TestFrame::new((4, 4), vec![1; 16]);
+        outside.origin = (2, 2);
+        let gif = encode_gif(
+            (3, 3),
+            &[[0, 0, 0], [255, 255, 255]],
+            &[TestFrame::new((1, 1), vec![1]), cleared, corner, outside],
+        );
+        let frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        let rect = |x, y, width, height| Rectangle::new(Point::new(x, y), Size::new(width, height));
+
+        assert_eq!(frames.dirty_area(0).unwrap(), rect(0, 0, 3, 3));
+        // The first frame stays in place, so only the new frame changes
+        assert_eq!(frames.dirty_area(1).unwrap(), rect(2, 0, 1, 1));
+        // Along with the area that the previous frame is cleared from
+        assert_eq!(frames.dirty_area(2).unwrap(), rect(0, 0, 3, 3));
+        // Clipped to the screen
+        assert_eq!(frames.dirty_area(3).unwrap(), rect(2, 2, 1, 1));
+        assert_eq!(frames.dirty_area(4), Err(ParseError::NoImagesLeft));
+    }
+
+    #[test]
+    fn bulk_decode_matches_iterator() {
+        let bee = include_bytes!("test/bee.gif");
+        let mut gif = GifFrameStreamer::from_slice(bee).unwrap();
+        for _ in 0..gif.num_images() {
+            gif.seek_to_next_frame().unwrap();
+            let expected: Vec<u8> = gif.current_frame().unwrap().indices().collect();
+            let mut buf = vec![0; expected.len()];
+            gif.current_frame()
+                .unwrap()
+                .decode_indices(&mut buf)
+                .unwrap();
+            assert_eq!(buf, expected);
+        }
+    }
+
+    #[test]
+    fn bulk_decode_interlaced() {
+        let indices: Vec<u8> = (0..27).map(|idx| idx % 4).collect();
+        let mut frame = TestFrame::new((3, 9), indices.clone());
+        frame.interlaced = true;
+        let gif = encode_gif((3, 9), &PALETTE, &[frame]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut frame = frames.next_frame().unwrap();
+        // Mixing in the iterator APIs must not lose any pixels
+        let mut buf = vec![0; 27];
+        buf[0] = frame.indices().next().unwrap();
+        frame.decode_indices(&mut buf).unwrap();
+        assert_eq!(buf, indices);
+    }
+
+    #[test]
+    fn bulk_decode_short_stream() {
+        for interlaced in [false, true] {
+            // The stream ends two pixels into the second row it stores
+            let mut frame = TestFrame::new((3, 3), vec![1; 5]);
+            frame.interlaced = interlaced;
+            let gif = encode_gif((3, 3), &PALETTE, &[frame]);
+            let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+            let mut frame = frames.next_frame().unwrap();
+            let mut buf = vec![7; 9];
+            frame.decode_indices(&mut buf).unwrap();
+            let expected = if interlaced {
+                // The second row stored is the bottom one
+                [1, 1, 1, 0, 0, 0, 1, 1, 0]
+            } else {
+                [1, 1, 1, 1, 1, 0, 0, 0, 0]
+            };
+            assert_eq!(buf, expected, "interlaced: {}", interlaced);
+        }
+    }
+
+    #[test]
+    fn bulk_decode_to_screen() {
+        let mut frame = TestFrame::new((3, 2), vec![1, 2, 3, 3, 2, 1]);
+        frame.origin = (2, 1);
+        let gif = encode_gif((4, 3), &PALETTE, &[frame]);
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+
+        let mut screen = [0; 12];
+        frames
+            .next_frame()
+            .unwrap()
+            .decode_indices_to_screen(&mut screen, 4)
+            .unwrap();
+        #[rustfmt::skip]
+        assert_eq!(screen, [
+            0, 0, 0, 0,
+            0, 0, 1, 2,
+            0, 0, 3, 2,
+        ]);
+
+        let res = frames
+            .current_frame()
+            .unwrap()
+            .decode_indices_to_screen(&mut screen[..11], 4);
+        assert_eq!(res, Err(ParseError::BufferTooSmall));
+    }
+
+    #[test]
+    fn small_decode_buffer() {
+        let bee = include_bytes!("test/bee.gif");
+        let mut small = GifFrameStreamer::<_, 16>::from_slice_with_buffer(bee).unwrap();
+        let mut gif = GifFrameStreamer::from_slice(bee).unwrap();
+        for _ in 0..gif.num_images() {
+            let expected: Vec<Rgb565> = gif.next_frame().unwrap().collect();
+            let pixels: Vec<Rgb565> = small.next_frame().unwrap().collect();
+            assert_eq!(pixels, expected);
+        }
+    }
+
+    #[test]
+    fn copying_and_zero_copy_sources_agree() {
+        let crab = include_bytes!("test/crab.gif");
+        let mut bytes = ByteIterator::new(CopyingIter::new(crab));
+        let gif_info = GifInfo::parser(&mut bytes).unwrap();
+        bytes.seek_to(0).unwrap();
+        let mut copying = GifFrameStreamer::new(gif_info, bytes);
+        let mut zero_copy = GifFrameStreamer::from_slice(crab).unwrap();
+
+        for _ in 0..zero_copy.num_images() {
+            let mut frame = zero_copy.next_frame().unwrap();
+            let mut expected = vec![0; frame.num_pixels()];
+            frame.decode_indices(&mut expected).unwrap();
+            let mut frame = copying.next_frame().unwrap();
+            let mut indices = vec![0; frame.num_pixels()];
+            frame.decode_indices(&mut indices).unwrap();
+            assert_eq!(indices, expected);
+        }
+    }
+
+    #[test]
+    fn draw_in_steps() {
+        let bee = include_bytes!("test/bee.gif");
+        let mut gif = GifFrameStreamer::from_slice(bee).unwrap();
+        let mut stepped_gif = GifFrameStreamer::from_slice(bee).unwrap();
+        let mut fb = Framebuffer::<240, 240>::new();
+        let mut stepped_fb = Framebuffer::<240, 240>::new();
+
+        for _ in 0..gif.num_images() {
+            gif.seek_to_next_frame().unwrap();
+            Image::new(&gif, Point::zero()).draw(&mut fb).unwrap();
+
+            let mut frame = stepped_gif.next_frame().unwrap();
+            let mut steps = 1;
+            while frame.draw_step(&mut stepped_fb, 1000).unwrap() == DrawProgress::Incomplete {
+                steps += 1;
+            }
+            assert_eq!(steps, (frame.num_pixels() + 999) / 1000);
+            assert_eq!(
+                frame.draw_step(&mut stepped_fb, 1000),
+                Ok(DrawProgress::Complete)
+            );
+        }
+
+        for y in 0..240 {
+            for x in 0..240 {
+                let point = Point::new(x, y);
+                assert_eq!(stepped_fb.pixel(point), fb.pixel(point));
+            }
+        }
+    }
+
+    #[cfg(feature = "weezl")]
+    #[test]
+    fn weezl_decoder() {
+        let crab = include_bytes!("test/crab.gif");
+        let mut gif = GifFrameStreamer::from_slice(crab).unwrap();
+        let mut weezl_gif =
+            GifFrameStreamer::<_, DEFAULT_DECODE_BUFFER_SIZE, _>::from_slice_with_decoder(
+                crab,
+                crate::lzw::WeezlDecoder::default(),
+            )
+            .unwrap();
+        for _ in 0..gif.num_images() {
+            let expected: Vec<Rgb565> = gif.next_frame().unwrap().collect();
+            let pixels: Vec<Rgb565> = weezl_gif.next_frame().unwrap().collect();
+            assert_eq!(pixels, expected);
+        }
+    }
 }
@@ -7,28 +7,124 @@ use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
 
 use crate::iterators::SeekableIter;
+use crate::palette::{NoTransform, PaletteTransform};
 
 use smallvec::SmallVec;
 pub struct GifInfo {
     header: Header,
     control_info: Option<GraphicsControl>,
-    pub(crate) image_block_locations: SmallVec<[usize; 128]>,
+    /// Loop count of the NETSCAPE2.0 application extension, if the file has one
+    loop_count: Option<u16>,
+    /// Everything that is kept about each image, 16 bytes apiece
+    frames: SmallVec<[FrameEntry; 128]>,
+}
+
+/// Where an image is stored and goes on the logical screen, its graphics control extension and
+/// how it relates to the images before it, packed into 16 bytes
+#[derive(Debug, Copy, Clone)]
+struct FrameEntry {
+    /// Offset of the image descriptor in the file
+    offset: u32,
+    left: u16,
+    top: u16,
+    width: u16,
+    height: u16,
+    /// Delay of the graphics control extension, in hundredths of a second
+    delay: u16,
+    /// Packed field of the graphics control extension, with the flags below in its reserved bits
+    ctrl: u8,
+    transparent_idx: u8,
+}
+
+impl FrameEntry {
+    /// The image has a graphics control extension
+    const HAS_CONTROL: u8 = 0x20;
+    /// The image covers the whole screen without transparency
+    const OPAQUE_OVER_SCREEN: u8 = 0x40;
+    /// Drawing can start over at the image, see [`GifInfo::is_key_frame`]
+    const KEY_FRAME: u8 = 0x80;
+
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(self.left as i32, self.top as i32),
+            Size::new(self.width as u32, self.height as u32),
+        )
+    }
+
+    fn has(&self, flag: u8) -> bool {
+        self.ctrl & flag != 0
+    }
+
+    fn control(&self) -> Option<GraphicsControl> {
+        self.has(Self::HAS_CONTROL).then(|| GraphicsControl {
+            ctrl: self.ctrl & 0x1f,
+            transparent_idx: self.transparent_idx,
+            delay_time: self.delay as u32 * 10,
+        })
+    }
 }
 
 impl GifInfo {
     pub fn parser<S: SeekableIter>(raw_header: &mut ByteIterator<S>) -> Result<Self, ParseError> {
         let header = Header::parser(raw_header)?;
-        let mut image_block_locations: SmallVec<[usize; 128]> = SmallVec::new();
+        let mut frames: SmallVec<[FrameEntry; 128]> = SmallVec::new();
+        // Whether the previous image is cleared to the background across the whole screen
+        let mut screen_cleared = true;
+
+        let screen = Rectangle::new(Point::zero(), header.image_size);
 
+        let mut loop_count = None;
         let mut control_info = None;
+        // A graphics control extension only applies to the image that follows it
+        let mut next_control: Option<GraphicsControl> = None;
         loop {
             let block_id = raw_header.take_byte().map(|byte| Block::from_u8(byte))??;
 
             match block_id {
                 Block::Image => {
-                    image_block_locations.push(raw_header.get_offset());
+                    let offset = raw_header.get_offset() as u32;
+                    let control = next_control.take();
                     //TODO:make this one call
-                    let _ = LocalImageDescriptor::parser(raw_header)?;
+                    let descriptor = LocalImageDescriptor::parser(raw_header, &NoTransform)?;
+                    let area = descriptor.bounding_box().intersection(&screen);
+                    let covers_screen = area == screen;
+                    let transparent = matches!(
+                        control.map(|control| control.get_transparent_idx()),
+                        Some(Some(_))
+                    );
+                    let opaque_over_screen = covers_screen && !transparent;
+                    let mut flags = 0;
+                    if control.is_some() {
+                        flags |= FrameEntry::HAS_CONTROL;
+                    }
+                    if opaque_over_screen {
+                        flags |= FrameEntry::OPAQUE_OVER_SCREEN;
+                    }
+                    let restores_previous = matches!(
+                        control.map(|control| control.disposal_method()),
+                        Some(DisposalMethod::OverwriteWithPrev)
+                    );
+                    // Restoring an image that covers the screen needs the screen from before it,
+                    // unless that was the background anyway
+                    if screen_cleared || (opaque_over_screen && !restores_previous) {
+                        flags |= FrameEntry::KEY_FRAME;
+                    }
+                    let (origin, size) = (descriptor.origin(), descriptor.size());
+                    frames.push(FrameEntry {
+                        offset,
+                        left: origin.x as u16,
+                        top: origin.y as u16,
+                        width: size.width as u16,
+                        height: size.height as u16,
+                        delay: control.map_or(0, |control| (control.delay_time / 10) as u16),
+                        ctrl: control.map_or(0, |control| control.ctrl & 0x1f) | flags,
+                        transparent_idx: control.map_or(0, |control| control.transparent_idx),
+                    });
+                    screen_cleared = covers_screen
+                        && matches!(
+                            control.map(|control| control.disposal_method()),
+                            Some(DisposalMethod::OverwriteWithBG)
+                        );
 
                     skip_image_data(raw_header)?;
                 }
@@ -40,7 +136,14 @@ impl GifInfo {
                         .map(|byte| ExtensionLabel::from_u8(byte))??;
                     match extension {
                         ExtensionLabel::Graphics => {
-                            control_info = Some(GraphicsControl::parse(raw_header)?);
+                            let control = GraphicsControl::parse(raw_header)?;
+                            control_info = Some(control);
+                            next_control = Some(control);
+                        }
+                        ExtensionLabel::App => {
+                            if let Some(count) = parse_app_extension(raw_header)? {
+                                loop_count = Some(count);
+                            }
                         }
                         _ => {
                             eat_extension(extension, raw_header)?;
@@ -52,8 +155,9 @@ impl GifInfo {
 
         Ok(Self {
             header,
-            image_block_locations,
             control_info,
+            loop_count,
+            frames,
         })
     }
     /// Delay between showing each gif frame, in miliseconds
@@ -63,8 +167,25 @@ impl GifInfo {
             .map(|val| val.delay_time.clone() as usize)
             .unwrap_or(50)
     }
+    /// Delay after showing the `idx`th image, in milliseconds, if it has a graphics control
+    /// extension
+    pub(crate) fn frame_delay(&self, idx: usize) -> Option<u32> {
+        self.frame_control(idx).map(|control| control.delay_time)
+    }
+    /// Number of times the animation repeats after the first time, 0 meaning forever
+    pub(crate) fn loop_count(&self) -> Option<u16> {
+        self.loop_count
+    }
+    /// Graphics control extension of the `idx`th image, if it has one
+    pub(crate) fn frame_control(&self, idx: usize) -> Option<GraphicsControl> {
+        self.frames.get(idx).and_then(FrameEntry::control)
+    }
+    /// Offset of the `idx`th image's descriptor in the file
+    pub(crate) fn frame_offset(&self, idx: usize) -> Option<usize> {
+        self.frames.get(idx).map(|frame| frame.offset as usize)
+    }
     pub(crate) fn num_images(&self) -> usize {
-        self.image_block_locations.len()
+        self.frames.len()
     }
 
     pub(crate) fn base_size(&self) -> Size {
@@ -73,8 +194,105 @@ impl GifInfo {
     pub(crate) fn global_table(&self) -> Option<&ColorTable> {
         self.header.global_table.as_ref().map(|val| &val.table)
     }
+
+    /// Color of the global color table's background index, if there is a global table
+    pub(crate) fn background_color(&self) -> Option<Rgb565> {
+        let global = self.header.global_table.as_ref()?;
+        global.table.get(global.background_color)
+    }
+
+    /// Whether any frame asks to be disposed of with [`DisposalMethod::OverwriteWithPrev`]
+    pub(crate) fn restores_to_previous(&self) -> bool {
+        self.frames
+            .iter()
+            .filter_map(FrameEntry::control)
+            .any(|control| control.disposal_method() == DisposalMethod::OverwriteWithPrev)
+    }
+
+    /// Number of pixels needed to save what is under any frame disposed with
+    /// [`DisposalMethod::OverwriteWithPrev`], counting only the part on the logical screen when
+    /// `clip_to_screen` is set
+    pub(crate) fn max_restore_pixels(&self, clip_to_screen: bool) -> usize {
+        let screen = Rectangle::new(Point::zero(), self.base_size());
+        self.frames
+            .iter()
+            .filter(|frame| {
+                matches!(
+                    frame.control().map(|control| control.disposal_method()),
+                    Some(DisposalMethod::OverwriteWithPrev)
+                )
+            })
+            .map(|frame| {
+                let mut area = frame.bounding_box();
+                if clip_to_screen {
+                    area = area.intersection(&screen);
+                }
+                area.size.width as usize * area.size.height as usize
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Whether drawing can start over at the `idx`th image, on a screen cleared to the background
+    ///
+    /// This holds for the image and for drawing the images that follow it, without any of the
+    /// images before it. That is the first image, one that follows an image cleared to the
+    /// background across the whole screen, or one that covers the whole screen without
+    /// transparency and isn't restored to the previous contents, as those would need the screen
+    /// from before it.
+    pub(crate) fn is_key_frame(&self, idx: usize) -> bool {
+        matches!(self.frames.get(idx), Some(frame) if frame.has(FrameEntry::KEY_FRAME))
+    }
+
+    /// Whether the `idx`th image covers the whole screen without transparency
+    pub(crate) fn is_opaque_over_screen(&self, idx: usize) -> bool {
+        matches!(self.frames.get(idx), Some(frame) if frame.has(FrameEntry::OPAQUE_OVER_SCREEN))
+    }
+
+    /// Area the `idx`th image declares it covers, which may extend past the logical screen
+    pub(crate) fn frame_area(&self, idx: usize) -> Option<Rectangle> {
+        self.frames.get(idx).map(FrameEntry::bounding_box)
+    }
+
+    /// Replaces the global color table, adding one if the file doesn't have it
+    pub(crate) fn set_global_table(&mut self, table: ColorTable) {
+        match self.header.global_table.as_mut() {
+            Some(global) => global.table = table,
+            None => {
+                self.header.global_table = Some(GlobalColorTable {
+                    table,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    /// Reads the global color table back out of `raw_header`, running it through `transform`
+    pub(crate) fn reload_global_table<S: SeekableIter, T: PaletteTransform>(
+        &mut self,
+        raw_header: &mut ByteIterator<S>,
+        transform: &T,
+    ) -> Result<(), ParseError> {
+        // The table may have been replaced, so its size is read from the header again
+        raw_header.seek_to(GLOBAL_TABLE_INFO_OFFSET)?;
+        let global_color_table_info = raw_header.take_byte()?;
+        if global_color_table_info.bitand(0x80).eq(&0) {
+            self.header.global_table = None;
+            return Ok(());
+        }
+        let num_entries = 1 << (global_color_table_info.bitand(0x7).add(1));
+        raw_header.seek_to(GLOBAL_TABLE_OFFSET)?;
+        let table = ColorTable::new(num_entries, raw_header, transform)?;
+        self.set_global_table(table);
+        Ok(())
+    }
 }
 
+/// Offset of the header's packed byte that describes the global color table
+const GLOBAL_TABLE_INFO_OFFSET: usize = 10;
+/// The global color table directly follows the 13 byte header
+const GLOBAL_TABLE_OFFSET: usize = 13;
+
 #[derive(Default)]
 pub struct Header {
     /// Gif size in pixels.
@@ -97,6 +315,10 @@ impl LocalImageDescriptor {
     pub(crate) fn color_table(&self) -> Option<&ColorTable> {
         self.local_color_table.as_ref()
     }
+    /// Replaces the frame's color table, used instead of the global one from now on
+    pub(crate) fn set_color_table(&mut self, table: ColorTable) {
+        self.local_color_table = Some(table);
+    }
     /// Total number of pixels in this frame
     pub(crate) fn num_pixels(&self) -> usize {
         (self.size.width * self.size.height) as usize
@@ -109,6 +331,11 @@ impl LocalImageDescriptor {
     pub(crate) fn origin(&self) -> Point {
         self.origin
     }
+    /// Whether the rows of this frame are stored in interlaced order
+    pub(crate) fn interlaced(&self) -> bool {
+        self.interlaced
+    }
+
     /// Area that the frame should be drawn to
     pub(crate) fn bounding_box(&self) -> Rectangle {
         Rectangle::new(self.origin, self.size)
@@ -124,48 +351,112 @@ pub struct GlobalColorTable {
 
 #[derive(Debug)]
 pub struct ColorTable {
+    /// Table entries as the RGB triples stored in the file, after the palette transform
+    rgb: SmallVec<[[u8; 3]; 256]>,
+    /// Same entries converted to the output format up front, so pixel lookups cost nothing extra
     pub(crate) table: SmallVec<[Rgb565; 256]>,
 }
 
-/// Process for displaying next image in the file
-///
-enum DisposalMethod {
+/// What happens to a frame's area once it has been shown, before the next frame is drawn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DisposalMethod {
+    /// No disposal specified, treated the same as [`DisposalMethod::DoNotDispose`]
     NotSpecified = 0,
+    /// The frame is left in place
     DoNotDispose = 1,
+    /// The frame's area is cleared to the background
     OverwriteWithBG = 2,
+    /// The frame's area is restored to what it was before the frame was drawn
     OverwriteWithPrev = 3,
 }
 
-#[derive(Debug)]
+impl DisposalMethod {
+    /// Decodes the 3 bit disposal field, values reserved by the spec map to `NotSpecified`
+    pub(crate) fn from_u8(val: u8) -> Self {
+        match val {
+            1 => Self::DoNotDispose,
+            2 => Self::OverwriteWithBG,
+            3 => Self::OverwriteWithPrev,
+            _ => Self::NotSpecified,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
 pub struct GraphicsControl {
     /// Control byte
     ctrl: u8,
     ///table index for a transparent color
     transparent_idx: u8,
-    ///Delay time, in second
-    delay_time: u16,
+    ///Delay time, in milliseconds
+    delay_time: u32,
 }
 
 impl Default for ColorTable {
     fn default() -> Self {
         Self {
+            rgb: SmallVec::new(),
             table: SmallVec::new(),
         }
     }
 }
 
 impl ColorTable {
-    pub fn new<S: SeekableIter>(len: u16, iter: &mut ByteIterator<S>) -> Result<Self, ParseError> {
-        let mut table = SmallVec::new();
+    pub fn new<S: SeekableIter, T: PaletteTransform>(
+        len: u16,
+        iter: &mut ByteIterator<S>,
+        transform: &T,
+    ) -> Result<Self, ParseError> {
+        let mut rgb = SmallVec::new();
 
         for _idx in 0..len {
             let r = iter.take_byte()?;
             let g = iter.take_byte()?;
             let b = iter.take_byte()?;
-            table.push(Rgb565::from(Rgb888::new(r, g, b)))
+            let color = transform.transform(Rgb888::new(r, g, b));
+            rgb.push([color.r(), color.g(), color.b()]);
         }
 
-        Ok(Self { table })
+        Ok(Self::from_rgb(rgb))
+    }
+
+    /// Builds a table out of caller supplied `colors` rather than reading it from a file
+    pub(crate) fn from_colors<T: PaletteTransform>(colors: &[Rgb888], transform: &T) -> Self {
+        let rgb = colors
+            .iter()
+            .take(256)
+            .map(|&color| {
+                let color = transform.transform(color);
+                [color.r(), color.g(), color.b()]
+            })
+            .collect();
+        Self::from_rgb(rgb)
+    }
+
+    fn from_rgb(rgb: SmallVec<[[u8; 3]; 256]>) -> Self {
+        let table = rgb
+            .iter()
+            .map(|&[r, g, b]| Rgb565::from(Rgb888::new(r, g, b)))
+            .collect();
+        Self { rgb, table }
+    }
+
+    /// Table entries as 24 bit RGB
+    pub(crate) fn rgb(&self) -> impl ExactSizeIterator<Item = Rgb888> + Clone + '_ {
+        self.rgb.iter().map(|&[r, g, b]| Rgb888::new(r, g, b))
+    }
+
+    /// Same as [`Self::get`], before conversion to [`Rgb565`]
+    #[inline]
+    pub(crate) fn get_rgb(&self, idx: u8) -> Option<Rgb888> {
+        let [r, g, b] = *self.rgb.get(idx as usize)?;
+        Some(Rgb888::new(r, g, b))
+    }
+
+    /// Looks up the color for palette index `idx`, if the table is large enough to contain it
+    #[inline]
+    pub(crate) fn get(&self, idx: u8) -> Option<Rgb565> {
+        self.table.get(idx as usize).copied()
     }
 }
 
@@ -184,15 +475,13 @@ impl Header {
 
         let global_color_table_info = raw_header.take_byte()?;
         let global_color_exists = global_color_table_info.bitand(0x80).eq(&0x80);
-        //FIXME: WHY DO WE NEED THIS? WE RANDOMLY SKIP TO THE 14th BYTE (IDX 13) IN THE ANIMATED GIF LIB??
-
-        raw_header.take_byte()?;
+        let background_color = raw_header.take_byte()?;
+        let _pixel_aspect_ratio = raw_header.take_byte()?;
 
         let global_table = if global_color_exists {
             let num_entries = 1 << (global_color_table_info.bitand(0x7).add(1));
             let bits_per_pixel = global_color_table_info.bitand(0x70).shr(4) + 1 as u8;
-            let background_color = raw_header.take_byte()?;
-            let table = ColorTable::new(num_entries, raw_header)?;
+            let table = ColorTable::new(num_entries, raw_header, &NoTransform)?;
             Some(GlobalColorTable {
                 background_color,
                 bits_per_pixel,
@@ -209,8 +498,9 @@ impl Header {
 }
 
 impl LocalImageDescriptor {
-    pub fn parser<S: SeekableIter>(
+    pub fn parser<S: SeekableIter, T: PaletteTransform>(
         raw_header: &mut ByteIterator<S>,
+        transform: &T,
     ) -> Result<LocalImageDescriptor, ParseError> {
         let left = raw_header.take_u16_le()? as i32;
         let top = raw_header.take_u16_le()? as i32;
@@ -219,29 +509,31 @@ impl LocalImageDescriptor {
         let height = raw_header.take_u16_le()? as u32;
 
         let size = Size { width, height };
-        let local_color_table = Self::maybe_parse_local_color_table(raw_header)?;
+        let (interlaced, local_color_table) =
+            Self::maybe_parse_local_color_table(raw_header, transform)?;
 
         Ok(Self {
             origin,
             size,
-            interlaced: false,
+            interlaced,
             local_color_table,
         })
     }
-    /// Helper for
-    fn maybe_parse_local_color_table<S: SeekableIter>(
+    /// Helper for parsing the interlace flag and the optional local color table that follows it
+    fn maybe_parse_local_color_table<S: SeekableIter, T: PaletteTransform>(
         raw_header: &mut ByteIterator<S>,
-    ) -> Result<Option<ColorTable>, ParseError> {
+        transform: &T,
+    ) -> Result<(bool, Option<ColorTable>), ParseError> {
         let color_info = raw_header.take_byte()?;
         let interlaced = color_info.bitand(0x40).eq(&0x40);
         let has_local_table = color_info.bitand(0x80).eq(&0x80);
         let local_table = if has_local_table {
             let num_entries = 1 << (color_info.bitand(0x07).add(1));
-            Some(ColorTable::new(num_entries, raw_header)?)
+            Some(ColorTable::new(num_entries, raw_header, transform)?)
         } else {
             None
         };
-        Ok(local_table)
+        Ok((interlaced, local_table))
     }
 }
 
@@ -251,7 +543,7 @@ impl GraphicsControl {
         //TODO: if len!= 4, throw error
         let ctrl = raw_header.take_byte()?;
 
-        let delay_time = raw_header.take_u16_le()? * 10;
+        let delay_time = raw_header.take_u16_le()? as u32 * 10;
 
         let transparent_idx = raw_header.take_byte()?;
 
@@ -265,11 +557,11 @@ impl GraphicsControl {
         })
     }
 
-    fn get_transparent_idx(&self) -> Option<u8> {
+    pub(crate) fn get_transparent_idx(&self) -> Option<u8> {
         self.ctrl.bitand(1).eq(&1).then_some(self.transparent_idx)
     }
-    fn disposal_method(&self) -> u8 {
-        self.ctrl.shr(2 as u8).bitand(0x2)
+    pub(crate) fn disposal_method(&self) -> DisposalMethod {
+        DisposalMethod::from_u8(self.ctrl.shr(2u8).bitand(0x7))
     }
 }
 
@@ -286,6 +578,42 @@ fn eat_extension<S: SeekableIter>(
     Ok(())
 }
 
+/// Parses an application extension, returning the loop count if it is a looping extension
+///
+/// The NETSCAPE2.0 extension (and its ANIMEXTS1.0 twin) carries the loop count in a sub-block
+/// that starts with 1; everything else is skipped.
+fn parse_app_extension<S: SeekableIter>(
+    raw_header: &mut ByteIterator<S>,
+) -> Result<Option<u16>, ParseError> {
+    let len = raw_header.take_byte()?;
+    if len != 11 {
+        raw_header.seek_by(len as usize)?;
+        eat_extension(ExtensionLabel::App, raw_header)?;
+        return Ok(None);
+    }
+    let identifier: [u8; 11] = raw_header.take_arr()?;
+    if identifier != *b"NETSCAPE2.0" && identifier != *b"ANIMEXTS1.0" {
+        eat_extension(ExtensionLabel::App, raw_header)?;
+        return Ok(None);
+    }
+
+    let mut loop_count = None;
+    loop {
+        match raw_header.take_byte()? {
+            0 => return Ok(loop_count),
+            len => {
+                let id = raw_header.take_byte()?;
+                if id == 1 && len >= 3 {
+                    loop_count = Some(raw_header.take_u16_le()?);
+                    raw_header.seek_by(len as usize - 3)?;
+                } else {
+                    raw_header.seek_by(len as usize - 1)?;
+                }
+            }
+        }
+    }
+}
+
 /// Quick shim
 fn skip_image_data<S: SeekableIter>(raw_header: &mut ByteIterator<S>) -> Result<(), ParseError> {
     let _code_size = raw_header.take_byte()?;
@@ -309,7 +637,80 @@ mod tests {
         let crab = include_bytes!("test/crab.gif");
         let mut iter = ByteIterator::from_slice(crab);
         let gif_info = GifInfo::parser(&mut iter).unwrap();
-        assert_eq!(gif_info.image_block_locations.len(), 60);
-        assert_eq!(gif_info.control_info.as_ref().unwrap().delay_time, 90)
+        assert_eq!(gif_info.num_images(), 60);
+        assert!((0..60).all(|idx| gif_info.frame_control(idx).is_some()));
+        assert_eq!(gif_info.frame_delay(59), Some(90));
+        assert_eq!(gif_info.control_info.as_ref().unwrap().delay_time, 90);
+        assert_eq!(core::mem::size_of::<FrameEntry>(), 16);
+    }
+
+    #[test]
+    fn background_color() {
+        use crate::test_utils::{encode_gif, TestFrame};
+
+        let table = [[0, 0, 0], [255, 255, 255]];
+        let mut gif = encode_gif((1, 1), &table, &[TestFrame::new((1, 1), vec![0])]);
+        // Background color index, followed by the pixel aspect ratio
+        gif[11] = 1;
+        gif[12] = 49;
+        let gif_info = GifInfo::parser(&mut ByteIterator::from_slice(&gif)).unwrap();
+        assert_eq!(gif_info.background_color(), Some(Rgb565::WHITE));
+        assert_eq!(gif_info.global_table().unwrap().table.len(), 2);
+    }
+
+    #[test]
+    fn key_frames() {
+        use crate::test_utils::{encode_gif, TestFrame};
+
+        let mut transparent = TestFrame::new((2, 2), vec![0, 1, 1, 1]);
+        transparent.transparent = Some(0);
+        let mut cleared = TestFrame::new((2, 2), vec![1; 4]);
+        cleared.disposal = 2;
+        let mut small_cleared = TestFrame::new((1, 1), vec![1]);
+        small_cleared.disposal = 2;
+        let restored = || {
+            let mut restored = TestFrame::new((2, 2), vec![1; 4]);
+            restored.disposal = 3;
+            restored
+        };
+        let mut cleared_again = TestFrame::new((2, 2), vec![1; 4]);
+        cleared_again.disposal = 2;
+        let gif = encode_gif(
+            (2, 2),
+            &[[0, 0, 0], [255, 255, 255]],
+            &[
+                TestFrame::new((1, 2), vec![1; 2]),
+                transparent,
+                cleared,
+                small_cleared,
+                TestFrame::new((1, 1), vec![1]),
+                TestFrame::new((2, 2), vec![0; 4]),
+                restored(),
+                cleared_again,
+                restored(),
+            ],
+        );
+        let gif_info = GifInfo::parser(&mut ByteIterator::from_slice(&gif)).unwrap();
+        let key_frames: SmallVec<[bool; 9]> = (0..gif_info.num_images())
+            .map(|idx| gif_info.is_key_frame(idx))
+            .collect();
+        // Restoring a frame that covers the screen needs the screen from before it, which is only
+        // known once the screen was cleared
+        assert_eq!(
+            &key_frames[..],
+            [true, false, true, true, false, true, false, true, true]
+        );
+        let opaque: SmallVec<[bool; 9]> = (0..gif_info.num_images())
+            .map(|idx| gif_info.is_opaque_over_screen(idx))
+            .collect();
+        assert_eq!(
+            &opaque[..],
+            [false, false, true, false, false, true, true, true, true]
+        );
+        assert_eq!(
+            gif_info.frame_area(0),
+            Some(Rectangle::new(Point::zero(), Size::new(1, 2)))
+        );
+        assert_eq!(gif_info.frame_area(9), None);
     }
 }
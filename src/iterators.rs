@@ -15,6 +15,13 @@ pub trait SeekableIter: Iterator<Item = u8> + Clone {
     fn seek(&mut self, offset: usize) -> Result<(), usize>;
     /// Moves forward by `len` bytes
     fn move_by(&mut self, len: usize) -> Result<(), usize>;
+    /// The rest of the stream as one contiguous slice, for sources that are backed by memory
+    ///
+    /// Readers use this to consume data in place rather than pulling it out a byte at a time.
+    /// The default implementation returns `None`.
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
 }
 
 impl<'a> Clone for SeekableSliceIter<'a> {
@@ -44,15 +51,25 @@ impl SeekableIter for SeekableSliceIter<'_> {
         //lv.map(|_| ()).ok_or(offset.into())
     }
     fn move_by(&mut self, len: usize) -> Result<(), usize> {
-        for i in 0..len {
-            self.1.next().ok_or(i)?;
+        let rest = self.1.as_slice();
+        match rest.get(len..) {
+            Some(rest) => {
+                self.1 = rest.iter();
+                Ok(())
+            }
+            None => {
+                self.1 = rest[rest.len()..].iter();
+                Err(rest.len())
+            }
         }
-        Ok(())
+    }
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.1.as_slice())
     }
 }
 
 impl<'a> SeekableSliceIter<'a> {
-    fn new(slice: &'a [u8]) -> Self {
+    pub(crate) fn new(slice: &'a [u8]) -> Self {
         Self(slice, slice.iter())
     }
 }
@@ -67,6 +84,18 @@ impl<'a> ByteIterator<SeekableSliceIter<'a>> {
 }
 
 impl<S: SeekableIter> ByteIterator<S> {
+    pub fn new(iterator: S) -> Self {
+        Self {
+            iterator,
+            offset: 0,
+        }
+    }
+
+    /// Unread part of the source, if it is backed by a slice
+    pub(crate) fn remaining_slice(&self) -> Option<&[u8]> {
+        self.iterator.as_slice()
+    }
+
     pub(crate) fn take_u16_le(&mut self) -> Result<u16, ParseError> {
         self.offset += size_of::<u16>();
         let mut holder: [u8; 2] = [0, 0];
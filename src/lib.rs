@@ -25,32 +25,67 @@
 //#![deny(missing_docs)]
 //#![feature(iter_next_chunk)]
 //#![feature(iter_advance_by)]
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
 pub use iterators::SeekableIter;
+use lzw::LzwDecoder;
 
+mod cached;
 mod common;
+mod compositor;
+mod dither;
 mod frame;
 mod header;
 mod iterators;
+pub mod lzw;
+mod palette;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod parser;
+mod player;
 #[cfg(test)]
 mod test_utils;
 
-pub use frame::{GifFrame, GifFrameStreamer};
+pub use cached::CachedFrame;
+pub use common::ParseError;
+pub use compositor::{CompositeError, Compositor, ReadbackTarget};
+pub use dither::{Dither, LumaColor};
+pub use frame::{
+    DitheredPixels, DrawProgress, FrameAlpha, FrameIndices, FramePixels, GifFrame,
+    GifFrameStreamer, DEFAULT_DECODE_BUFFER_SIZE,
+};
+pub use header::DisposalMethod;
+pub use palette::{
+    BorrowedPalettes, Brightness, ChannelLut, ColorDistance, ColorMatrix, NoOverrides, NoTransform,
+    PaletteOverrides, PaletteRemap, PaletteTransform,
+};
+#[cfg(feature = "rayon")]
+pub use parallel::{decode_frames, DecodedFrame, DecodedGif};
+pub use player::{Direction, Loops, Player, Tick, MIN_DELAY_MS, SHORT_DELAY_MS};
 
-impl<S> ImageDrawable for GifFrameStreamer<S>
+impl<S, const DECODE_BUF: usize, L, T, P> ImageDrawable for GifFrameStreamer<S, DECODE_BUF, L, T, P>
 where
     S: SeekableIter,
+    L: LzwDecoder,
+    T: PaletteTransform,
+    P: PaletteOverrides,
 {
     type Color = Rgb565;
     fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = Rgb565>,
     {
-        let frame = self.current_frame().unwrap();
+        // Nothing is drawn until a frame was seeked to
+        let mut frame = match self.current_frame() {
+            Ok(frame) => frame,
+            Err(_) => return Ok(()),
+        };
         let area = frame.frame_area();
-        target.fill_contiguous(&area, frame)
+        if frame.clipped_area() == area && !frame.interlaced() {
+            target.fill_contiguous(&area, frame)
+        } else {
+            target.draw_iter(frame.pixels())
+        }
     }
 
     fn draw_sub_image<D>(&self, _target: &mut D, _area: &Rectangle) -> Result<(), D::Error>
@@ -61,9 +96,13 @@ where
     }
 }
 
-impl<S> OriginDimensions for GifFrameStreamer<S>
+impl<S, const DECODE_BUF: usize, L, T, P> OriginDimensions
+    for GifFrameStreamer<S, DECODE_BUF, L, T, P>
 where
     S: SeekableIter,
+    L: LzwDecoder,
+    T: PaletteTransform,
+    P: PaletteOverrides,
 {
     fn size(&self) -> Size {
         self.base_size()
@@ -0,0 +1,480 @@
+//! LZW decoding of GIF image data
+//!
+//! Frames run their image data through an [`LzwDecoder`], so targets can pick the implementation
+//! that suits them. [`Decoder`] is the crate's own allocation free decoder. Its string table has a
+//! fixed 4096 entries, the most that 12 bit codes can address. Each entry only stores the code it
+//! extends and its last byte; strings are measured by walking their prefixes, then written back to
+//! front straight into the output buffer without an intermediate stack. A string that doesn't fit
+//! is measured once, resuming it later only walks past the bytes that come after the next chunk.
+//!
+//! With the `weezl` feature enabled, [`WeezlDecoder`] adapts the `weezl` crate's decoder instead,
+//! which is faster but needs an allocator.
+
+/// Number of codes addressable with the maximum code size
+const MAX_CODES: usize = 4096;
+/// Largest code size, in bits, that GIF allows
+const MAX_CODE_SIZE: u8 = 12;
+
+/// Status of the decoder after a call to [`Decoder::decode_bytes`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LzwStatus {
+    /// Input was consumed or output produced, and the stream is not done yet
+    Ok,
+    /// Neither input was consumed nor output produced; more input is needed
+    NoProgress,
+    /// The end code was reached
+    Done,
+}
+
+/// Errors in the LZW stream
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LzwError {
+    /// A code referred to a table entry that does not exist yet
+    InvalidCode,
+    /// The minimum code size is outside of the 1 to 11 bits that leave room for any codes
+    InvalidCodeSize,
+}
+
+/// Outcome of a call to [`Decoder::decode_bytes`]
+#[derive(Debug)]
+pub struct BufferResult {
+    /// Number of input bytes that were consumed
+    pub consumed_in: usize,
+    /// Number of bytes written to the output buffer
+    pub consumed_out: usize,
+    pub status: Result<LzwStatus, LzwError>,
+}
+
+/// Decoder for the LZW stream of a single frame at a time
+pub trait LzwDecoder {
+    /// Prepares the decoder for a new stream with the given minimum code size
+    ///
+    /// Fails with [`LzwError::InvalidCodeSize`] if the stream can't be decoded at that size.
+    fn reset(&mut self, min_code_size: u8) -> Result<(), LzwError>;
+
+    /// Decodes as much of `inp` into `out` as possible
+    ///
+    /// Output that doesn't fit in `out` must be kept until the next call, and a decoder may hold
+    /// on to output until it is called again even if there is room; it is only out of input once
+    /// it reports [`LzwStatus::NoProgress`].
+    fn decode_bytes(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult;
+}
+
+/// Checks that `min_code_size` leaves room for the literals, the clear and end codes and at least
+/// one table entry within 12 bit codes
+fn check_code_size(min_code_size: u8) -> Result<(), LzwError> {
+    match min_code_size {
+        1..=11 => Ok(()),
+        _ => Err(LzwError::InvalidCodeSize),
+    }
+}
+
+/// The crate's own allocation free LZW decoder, taking up roughly 12 KiB
+pub struct Decoder {
+    /// Code whose string each entry extends by one byte
+    prefix: [u16; MAX_CODES],
+    /// Last byte of each entry's string
+    suffix: [u8; MAX_CODES],
+    /// 0 after [`Self::reset`] was given an invalid code size, which fails decoding
+    min_code_size: u8,
+    code_size: u8,
+    /// Next table entry to be assigned
+    next_code: u16,
+    /// Previously decoded code, `None` right after a clear code
+    prev_code: Option<u16>,
+    /// First byte of the previous code's string
+    prev_first: u8,
+    /// Input bits that don't make up a whole code yet
+    bits: u32,
+    num_bits: u8,
+    /// Code whose string didn't fit in the output, how many of its bytes were written and its
+    /// length, so that resuming doesn't measure the string again
+    pending: Option<(u16, u16, u16)>,
+    done: bool,
+}
+
+impl Decoder {
+    /// Creates a decoder for a stream with the given minimum code size
+    ///
+    /// An invalid code size makes [`Self::decode_bytes`] fail until the next valid reset.
+    pub fn new(min_code_size: u8) -> Self {
+        let mut decoder = Self {
+            prefix: [0; MAX_CODES],
+            suffix: [0; MAX_CODES],
+            min_code_size: 0,
+            code_size: 0,
+            next_code: 0,
+            prev_code: None,
+            prev_first: 0,
+            bits: 0,
+            num_bits: 0,
+            pending: None,
+            done: false,
+        };
+        let _ = decoder.reset(min_code_size);
+        decoder
+    }
+
+    /// Prepares the decoder for a new stream with the given minimum code size
+    pub fn reset(&mut self, min_code_size: u8) -> Result<(), LzwError> {
+        self.bits = 0;
+        self.num_bits = 0;
+        self.pending = None;
+        self.done = false;
+        if let Err(err) = check_code_size(min_code_size) {
+            self.min_code_size = 0;
+            return Err(err);
+        }
+
+        self.min_code_size = min_code_size;
+        for code in 0..self.clear_code() {
+            self.suffix[code as usize] = code as u8;
+        }
+        self.clear_table();
+        Ok(())
+    }
+
+    fn clear_code(&self) -> u16 {
+        1 << self.min_code_size
+    }
+
+    fn end_code(&self) -> u16 {
+        self.clear_code() + 1
+    }
+
+    fn clear_table(&mut self) {
+        self.code_size = self.min_code_size + 1;
+        self.next_code = self.end_code() + 1;
+        self.prev_code = None;
+    }
+
+    /// Decodes codes from `inp` into `out` until either runs out or the end code is reached
+    pub fn decode_bytes(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        let mut consumed_in = 0;
+        let mut written = 0;
+        if self.min_code_size == 0 {
+            return BufferResult {
+                consumed_in,
+                consumed_out: written,
+                status: Err(LzwError::InvalidCodeSize),
+            };
+        }
+
+        if let Some((code, emitted, len)) = self.pending {
+            written = self.write_string(code, emitted as usize, len as usize, out);
+        }
+
+        let status = loop {
+            if self.done {
+                break Ok(LzwStatus::Done);
+            }
+            if written == out.len() {
+                break Ok(LzwStatus::Ok);
+            }
+
+            while self.num_bits < self.code_size && consumed_in < inp.len() {
+                self.bits |= (inp[consumed_in] as u32) << self.num_bits;
+                self.num_bits += 8;
+                consumed_in += 1;
+            }
+            if self.num_bits < self.code_size {
+                break Ok(LzwStatus::Ok);
+            }
+            let code = (self.bits & ((1 << self.code_size) - 1)) as u16;
+            self.bits >>= self.code_size;
+            self.num_bits -= self.code_size;
+
+            if code == self.clear_code() {
+                self.clear_table();
+                continue;
+            }
+            if code == self.end_code() {
+                self.done = true;
+                continue;
+            }
+
+            let prev_code = match self.prev_code {
+                Some(prev_code) => prev_code,
+                None if code < self.clear_code() => {
+                    written += self.write_code(code, &mut out[written..]);
+                    self.prev_code = Some(code);
+                    continue;
+                }
+                None => break Err(LzwError::InvalidCode),
+            };
+
+            let table_full = self.next_code as usize == MAX_CODES;
+            if code == self.next_code && !table_full {
+                // The code is being defined by this very step: it is the previous string followed
+                // by that string's first byte
+                self.add_entry(prev_code, self.prev_first);
+                written += self.write_code(code, &mut out[written..]);
+            } else if code < self.next_code {
+                written += self.write_code(code, &mut out[written..]);
+                // Once the table is full, the encoder may keep going without sending a clear code.
+                // Codes are then fixed at 12 bits and no new entries are made.
+                if !table_full {
+                    self.add_entry(prev_code, self.prev_first);
+                }
+            } else {
+                break Err(LzwError::InvalidCode);
+            }
+            self.prev_code = Some(code);
+        };
+
+        let status = match status {
+            Ok(LzwStatus::Ok) if consumed_in == 0 && written == 0 => Ok(LzwStatus::NoProgress),
+            status => status,
+        };
+        BufferResult {
+            consumed_in,
+            consumed_out: written,
+            status,
+        }
+    }
+
+    /// Adds the string of `prefix` followed by `byte` to the table
+    fn add_entry(&mut self, prefix: u16, byte: u8) {
+        let code = self.next_code as usize;
+        self.prefix[code] = prefix;
+        self.suffix[code] = byte;
+        self.next_code += 1;
+        if self.next_code == 1 << self.code_size && self.code_size < MAX_CODE_SIZE {
+            self.code_size += 1;
+        }
+    }
+
+    /// Number of bytes in the string of `code`, found by following its prefixes down to a literal
+    fn string_len(&self, code: u16) -> usize {
+        let mut len = 1;
+        let mut link = code;
+        while link > self.end_code() {
+            link = self.prefix[link as usize];
+            len += 1;
+        }
+        len
+    }
+
+    /// Writes the string of `code` to the start of `out`, see [`Self::write_string`]
+    fn write_code(&mut self, code: u16, out: &mut [u8]) -> usize {
+        let len = self.string_len(code);
+        self.write_string(code, 0, len, out)
+    }
+
+    /// Writes the string of `code`, `len` bytes long, starting at its `skip`th byte to the start
+    /// of `out`
+    ///
+    /// Returns the number of bytes written. Whatever doesn't fit is kept as pending output for the
+    /// next call to [`Self::decode_bytes`].
+    fn write_string(&mut self, code: u16, skip: usize, len: usize, out: &mut [u8]) -> usize {
+        let count = (len - skip).min(out.len());
+
+        // Strings are linked from their last byte to their first, so walk past the bytes that
+        // don't fit before writing back to front
+        let mut link = code;
+        for _ in 0..len - skip - count {
+            link = self.prefix[link as usize];
+        }
+        for byte in out[..count].iter_mut().rev() {
+            *byte = self.suffix[link as usize];
+            link = self.prefix[link as usize];
+        }
+
+        if skip == 0 && count > 0 {
+            self.prev_first = out[0];
+        }
+        self.pending = (skip + count < len).then_some((code, (skip + count) as u16, len as u16));
+        count
+    }
+}
+
+impl LzwDecoder for Decoder {
+    fn reset(&mut self, min_code_size: u8) -> Result<(), LzwError> {
+        Decoder::reset(self, min_code_size)
+    }
+
+    fn decode_bytes(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        Decoder::decode_bytes(self, inp, out)
+    }
+}
+
+/// Adapter for the decoder of the `weezl` crate
+#[cfg(feature = "weezl")]
+pub struct WeezlDecoder(weezl::decode::Decoder);
+
+#[cfg(feature = "weezl")]
+impl Default for WeezlDecoder {
+    fn default() -> Self {
+        Self(weezl::decode::Decoder::new(weezl::BitOrder::Lsb, 8))
+    }
+}
+
+#[cfg(feature = "weezl")]
+impl LzwDecoder for WeezlDecoder {
+    fn reset(&mut self, min_code_size: u8) -> Result<(), LzwError> {
+        check_code_size(min_code_size)?;
+        // weezl only resets the stream state, so the code size needs a fresh decoder
+        self.0 = weezl::decode::Decoder::new(weezl::BitOrder::Lsb, min_code_size);
+        Ok(())
+    }
+
+    fn decode_bytes(&mut self, inp: &[u8], out: &mut [u8]) -> BufferResult {
+        let res = self.0.decode_bytes(inp, out);
+        BufferResult {
+            consumed_in: res.consumed_in,
+            consumed_out: res.consumed_out,
+            status: match res.status {
+                Ok(weezl::LzwStatus::Ok) => Ok(LzwStatus::Ok),
+                Ok(weezl::LzwStatus::NoProgress) => Ok(LzwStatus::NoProgress),
+                Ok(weezl::LzwStatus::Done) => Ok(LzwStatus::Done),
+                Err(_) => Err(LzwError::InvalidCode),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    fn decode_chunked(
+        min_code_size: u8,
+        data: &[u8],
+        in_chunk: usize,
+        out_chunk: usize,
+    ) -> Vec<u8> {
+        let mut decoder = Decoder::new(min_code_size);
+        let mut out = Vec::new();
+        let mut buf = vec![0; out_chunk];
+        let mut inp = data;
+        loop {
+            let res = decoder.decode_bytes(&inp[..in_chunk.min(inp.len())], &mut buf);
+            inp = &inp[res.consumed_in..];
+            out.extend_from_slice(&buf[..res.consumed_out]);
+            match res.status.unwrap() {
+                LzwStatus::Done => return out,
+                LzwStatus::NoProgress if inp.is_empty() => return out,
+                _ => {}
+            }
+        }
+    }
+
+    fn test_data(len: usize, symbols: u32) -> Vec<u8> {
+        // Mix of runs, which exercise codes defined by the step that uses them, and noise
+        let mut state = 0x1234_5678u32;
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let symbol = ((state >> 16) % symbols) as u8;
+            let run = if state & 0x8000 != 0 {
+                (state >> 8) % 40
+            } else {
+                1
+            };
+            data.extend((0..run).map(|_| symbol));
+        }
+        data.truncate(len);
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        for min_code_size in 2..=8 {
+            let data = test_data(20_000, 1 << min_code_size);
+            let encoded = weezl::encode::Encoder::new(weezl::BitOrder::Lsb, min_code_size)
+                .encode(&data)
+                .unwrap();
+            for (in_chunk, out_chunk) in [(usize::MAX, 1 << 16), (255, 1024), (1, 1), (7, 3)] {
+                let decoded = decode_chunked(min_code_size, &encoded, in_chunk, out_chunk);
+                assert_eq!(
+                    decoded, data,
+                    "code size {min_code_size}, chunks {out_chunk}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn long_strings_in_small_chunks() {
+        // A single color makes every code one byte longer than the one before, and once the
+        // table is full without a clear code the longest strings keep being reused
+        let data = vec![3; 1 << 20];
+        let encoded = weezl::encode::Encoder::new(weezl::BitOrder::Lsb, 2)
+            .encode(&data)
+            .unwrap();
+        assert_eq!(decode_chunked(2, &encoded, 255, 16), data);
+    }
+
+    /// Packs codes LSB first, growing the code size the way the decoder expects
+    fn pack_codes(min_code_size: u8, codes: &[u16]) -> Vec<u8> {
+        let clear_code = 1u16 << min_code_size;
+        let mut code_size = min_code_size + 1;
+        let mut next_code = clear_code + 2;
+        let mut first = true;
+        let (mut bits, mut num_bits, mut out) = (0u32, 0u8, Vec::new());
+        for &code in codes {
+            bits |= (code as u32) << num_bits;
+            num_bits += code_size;
+            while num_bits >= 8 {
+                out.push(bits as u8);
+                bits >>= 8;
+                num_bits -= 8;
+            }
+
+            if code == clear_code {
+                code_size = min_code_size + 1;
+                next_code = clear_code + 2;
+                first = true;
+            } else if first {
+                first = false;
+            } else if (next_code as usize) < MAX_CODES {
+                next_code += 1;
+                if next_code == 1 << code_size && code_size < MAX_CODE_SIZE {
+                    code_size += 1;
+                }
+            }
+        }
+        out.push(bits as u8);
+        out
+    }
+
+    #[test]
+    fn deferred_clear_code() {
+        // Only literals, so the table fills up and the stream keeps going at 12 bits without a
+        // clear code, before finally clearing and dropping back to 3 bit codes
+        let literals: Vec<u16> = (0..6000).map(|idx| idx % 4).collect();
+        let mut codes = literals.clone();
+        codes.push(4);
+        codes.extend_from_slice(&[3, 2, 1, 0]);
+        codes.push(5);
+
+        let mut expected: Vec<u8> = literals.iter().map(|&code| code as u8).collect();
+        expected.extend_from_slice(&[3, 2, 1, 0]);
+        let encoded = pack_codes(2, &codes);
+        assert_eq!(decode_chunked(2, &encoded, 255, 1024), expected);
+    }
+
+    #[test]
+    fn invalid_code() {
+        // Code 7 doesn't exist yet when it is sent after a single literal
+        let encoded = pack_codes(2, &[1, 7]);
+        let mut decoder = Decoder::new(2);
+        let res = decoder.decode_bytes(&encoded, &mut [0; 16]);
+        assert_eq!(res.status.unwrap_err(), LzwError::InvalidCode);
+    }
+
+    #[test]
+    fn invalid_code_size() {
+        let encoded = pack_codes(2, &[1, 5]);
+        let mut decoder = Decoder::new(2);
+        for min_code_size in [0, 12, 255] {
+            assert_eq!(decoder.reset(min_code_size), Err(LzwError::InvalidCodeSize));
+            let res = decoder.decode_bytes(&encoded, &mut [0; 16]);
+            assert_eq!(res.status.unwrap_err(), LzwError::InvalidCodeSize);
+        }
+        assert_eq!(decoder.reset(2), Ok(()));
+        let res = decoder.decode_bytes(&encoded, &mut [0; 16]);
+        assert_eq!((res.status, res.consumed_out), (Ok(LzwStatus::Done), 1));
+    }
+}
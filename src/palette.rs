@@ -0,0 +1,311 @@
+use embedded_graphics::{pixelcolor::Rgb888, prelude::RgbColor};
+
+/// Adjusts palette colors as color tables are loaded, so pixels are output with no extra cost
+///
+/// Any `Fn(Rgb888) -> Rgb888` is a transform; [`ChannelLut`], [`Brightness`] and [`ColorMatrix`]
+/// cover the common cases without needing floating point math at load time. Transforms are
+/// applied to the global color table when they're set with
+/// [`GifFrameStreamer::set_palette_transform`], and to local color tables as each frame is
+/// loaded.
+///
+/// [`GifFrameStreamer::set_palette_transform`]: crate::GifFrameStreamer::set_palette_transform
+pub trait PaletteTransform {
+    fn transform(&self, color: Rgb888) -> Rgb888;
+}
+
+impl<F: Fn(Rgb888) -> Rgb888> PaletteTransform for F {
+    fn transform(&self, color: Rgb888) -> Rgb888 {
+        self(color)
+    }
+}
+
+/// Leaves every color as it is stored in the file
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoTransform;
+
+impl PaletteTransform for NoTransform {
+    #[inline]
+    fn transform(&self, color: Rgb888) -> Rgb888 {
+        color
+    }
+}
+
+/// Looks each channel up in its own table, for gamma correction and similar curves
+#[derive(Debug, Copy, Clone)]
+pub struct ChannelLut {
+    pub r: [u8; 256],
+    pub g: [u8; 256],
+    pub b: [u8; 256],
+}
+
+impl ChannelLut {
+    /// Uses the same table for all three channels
+    pub fn uniform(lut: [u8; 256]) -> Self {
+        Self {
+            r: lut,
+            g: lut,
+            b: lut,
+        }
+    }
+}
+
+impl PaletteTransform for ChannelLut {
+    fn transform(&self, color: Rgb888) -> Rgb888 {
+        Rgb888::new(
+            self.r[color.r() as usize],
+            self.g[color.g() as usize],
+            self.b[color.b() as usize],
+        )
+    }
+}
+
+/// Scales all channels by `level / 255`, where 255 leaves colors unchanged
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Brightness(pub u8);
+
+impl PaletteTransform for Brightness {
+    fn transform(&self, color: Rgb888) -> Rgb888 {
+        let scale = |channel: u8| ((channel as u32 * self.0 as u32 + 127) / 255) as u8;
+        Rgb888::new(scale(color.r()), scale(color.g()), scale(color.b()))
+    }
+}
+
+/// Mixes the channels with a 3x3 matrix of 8.8 fixed point factors, so 256 stands for 1.0
+///
+/// Each row produces one output channel from the input red, green and blue, results are clamped
+/// to the 0 to 255 range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorMatrix(pub [[i16; 3]; 3]);
+
+impl ColorMatrix {
+    pub const IDENTITY: Self = Self([[256, 0, 0], [0, 256, 0], [0, 0, 256]]);
+}
+
+impl PaletteTransform for ColorMatrix {
+    fn transform(&self, color: Rgb888) -> Rgb888 {
+        let input = [color.r(), color.g(), color.b()];
+        let channel = |row: &[i16; 3]| {
+            let sum: i32 = row
+                .iter()
+                .zip(input)
+                .map(|(&factor, value)| factor as i32 * value as i32)
+                .sum();
+            ((sum + 128) >> 8).clamp(0, 255) as u8
+        };
+        Rgb888::new(
+            channel(&self.0[0]),
+            channel(&self.0[1]),
+            channel(&self.0[2]),
+        )
+    }
+}
+
+/// Caller supplied palettes that are used instead of the color tables stored in the file
+///
+/// Overrides are looked up whenever a color table is loaded, and are run through the palette
+/// transform like any other table. Only the first 256 colors of a palette are used. The streamer
+/// holds on to the overrides themselves, so palettes can be borrowed rather than copied, see
+/// [`BorrowedPalettes`], and the default [`NoOverrides`] takes up no room at all.
+pub trait PaletteOverrides {
+    /// Palette to use instead of the global color table when `frame` is `None`, or instead of the
+    /// color table of the `frame`th frame
+    ///
+    /// Frames without a local color table get their palette in place of the global one.
+    fn palette(&self, frame: Option<usize>) -> Option<&[Rgb888]>;
+}
+
+/// Uses the color tables as they are stored in the file
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoOverrides;
+
+impl PaletteOverrides for NoOverrides {
+    #[inline]
+    fn palette(&self, _frame: Option<usize>) -> Option<&[Rgb888]> {
+        None
+    }
+}
+
+/// Palettes borrowed from the caller, for the global color table and any number of frames
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct BorrowedPalettes<'p> {
+    /// Used instead of the global color table
+    pub global: Option<&'p [Rgb888]>,
+    /// Used instead of the color table of the frame at the given index, the first one wins for
+    /// frames that are listed more than once
+    pub frames: &'p [(usize, &'p [Rgb888])],
+}
+
+impl PaletteOverrides for BorrowedPalettes<'_> {
+    fn palette(&self, frame: Option<usize>) -> Option<&[Rgb888]> {
+        match frame {
+            None => self.global,
+            Some(frame) => self
+                .frames
+                .iter()
+                .find(|(overridden, _)| *overridden == frame)
+                .map(|(_, palette)| *palette),
+        }
+    }
+}
+
+/// How the distance between two colors is measured when looking for the nearest one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorDistance {
+    /// Squared euclidean distance between the RGB values
+    Rgb,
+    /// The "redmean" approximation of perceived difference, which weighs the channels depending
+    /// on how red the colors are
+    Redmean,
+}
+
+impl ColorDistance {
+    fn between(self, a: Rgb888, b: Rgb888) -> u32 {
+        let delta = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+        let (dr, dg, db) = (
+            delta(a.r(), b.r()),
+            delta(a.g(), b.g()),
+            delta(a.b(), b.b()),
+        );
+        match self {
+            Self::Rgb => dr + dg + db,
+            Self::Redmean => {
+                let mean = (a.r() as u32 + b.r() as u32) / 2;
+                (((512 + mean) * dr) >> 8) + 4 * dg + (((767 - mean) * db) >> 8)
+            }
+        }
+    }
+
+    /// Index of the entry of `palette` closest to `color`, the first one on ties
+    pub fn nearest(self, palette: &[Rgb888], color: Rgb888) -> u8 {
+        palette
+            .iter()
+            .take(256)
+            .enumerate()
+            .min_by_key(|(_, &entry)| self.between(entry, color))
+            .map_or(0, |(idx, _)| idx as u8)
+    }
+}
+
+/// Maps the colors of GIF palettes to the nearest entries of a fixed hardware palette
+///
+/// Each palette is only searched once, the mapping of the global color table is kept around and
+/// reused by every frame that doesn't bring its own table. The mapping is tied to a fingerprint of
+/// the colors it was made from, so it is redone whenever the global table changes, be it through
+/// a palette override, a new palette transform or fallback color, or another file.
+pub struct PaletteRemap<'a> {
+    target: &'a [Rgb888],
+    distance: ColorDistance,
+    /// Mapping of the global color table, along with the fingerprint of its colors
+    global: Option<(u64, [u8; 256])>,
+}
+
+impl<'a> PaletteRemap<'a> {
+    /// Remaps to `target`, of which only the first 256 entries are used
+    pub fn new(target: &'a [Rgb888], distance: ColorDistance) -> Self {
+        Self {
+            target,
+            distance,
+            global: None,
+        }
+    }
+
+    /// Palette that colors are mapped to
+    pub fn target(&self) -> &'a [Rgb888] {
+        self.target
+    }
+
+    /// Forgets the mapping of the global color table, e.g. to free up the time of searching the
+    /// palette again at a convenient moment
+    pub fn invalidate(&mut self) {
+        self.global = None;
+    }
+
+    /// Target index for each index of `palette`, indices past its end are mapped like `fallback`
+    pub(crate) fn table<I>(&mut self, palette: I, global: bool, fallback: Rgb888) -> [u8; 256]
+    where
+        I: Iterator<Item = Rgb888> + Clone,
+    {
+        let key = fingerprint(palette.clone(), fallback);
+        match self.global {
+            Some((cached, table)) if global && cached == key => return table,
+            _ => {}
+        }
+        let fallback = self.distance.nearest(self.target, fallback);
+        let mut table = [fallback; 256];
+        for (entry, color) in table.iter_mut().zip(palette) {
+            *entry = self.distance.nearest(self.target, color);
+        }
+        if global {
+            self.global = Some((key, table));
+        }
+        table
+    }
+}
+
+/// 64 bit FNV-1a hash of the colors of `palette` followed by `fallback`
+fn fingerprint(palette: impl Iterator<Item = Rgb888>, fallback: Rgb888) -> u64 {
+    palette
+        .chain(core::iter::once(fallback))
+        .flat_map(|color| [color.r(), color.g(), color.b()])
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms() {
+        let color = Rgb888::new(10, 100, 200);
+        let invert = |color: Rgb888| Rgb888::new(!color.r(), !color.g(), !color.b());
+        assert_eq!(invert.transform(color), Rgb888::new(245, 155, 55));
+        assert_eq!(Brightness(0).transform(color), Rgb888::BLACK);
+
+        let mut lut = [0; 256];
+        lut.iter_mut()
+            .enumerate()
+            .for_each(|(n, entry)| *entry = (n / 2) as u8);
+        assert_eq!(
+            ChannelLut::uniform(lut).transform(color),
+            Rgb888::new(5, 50, 100)
+        );
+
+        assert_eq!(ColorMatrix::IDENTITY.transform(color), color);
+        // Swap red and blue, and double green
+        let matrix = ColorMatrix([[0, 0, 256], [0, 512, 0], [256, 0, 0]]);
+        assert_eq!(matrix.transform(color), Rgb888::new(200, 200, 10));
+    }
+
+    #[test]
+    fn nearest_color() {
+        let target = [
+            Rgb888::BLUE,
+            Rgb888::new(128, 0, 0),
+            Rgb888::new(0, 96, 0),
+            Rgb888::WHITE,
+        ];
+        let nearest = |distance: ColorDistance, color| distance.nearest(&target, color);
+        assert_eq!(nearest(ColorDistance::Rgb, Rgb888::new(20, 20, 200)), 0);
+        assert_eq!(nearest(ColorDistance::Rgb, Rgb888::new(200, 210, 220)), 3);
+        // Slightly closer to dark red in RGB, but differences in green weigh more
+        let color = Rgb888::new(64, 47, 0);
+        assert_eq!(nearest(ColorDistance::Rgb, color), 1);
+        assert_eq!(nearest(ColorDistance::Redmean, color), 2);
+    }
+
+    #[test]
+    fn remap_tells_apart_low_bits() {
+        // Both colors fall in the same Rgb565 level
+        let (dark, light) = (Rgb888::new(10, 0, 0), Rgb888::new(12, 0, 0));
+        let target = [dark, light];
+        let mut remap = PaletteRemap::new(&target, ColorDistance::Rgb);
+
+        let table = remap.table([dark, light].into_iter(), true, Rgb888::BLACK);
+        assert_eq!(table[..2], [0, 1]);
+        // A global table that only differs in its low bits is searched again
+        let table = remap.table([light, dark].into_iter(), true, Rgb888::BLACK);
+        assert_eq!(table[..2], [1, 0]);
+    }
+}
@@ -0,0 +1,323 @@
+use core::cell::RefCell;
+
+use embedded_graphics::{
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::{Point, PointsIter, RgbColor, Size},
+    primitives::Rectangle,
+};
+use rayon::prelude::*;
+
+use crate::{
+    common::ParseError,
+    frame::{GifFrame, DEFAULT_DECODE_BUFFER_SIZE},
+    header::{DisposalMethod, GifInfo, LocalImageDescriptor},
+    iterators::ByteIterator,
+    lzw::Decoder,
+    palette::NoTransform,
+};
+
+/// Every frame of a GIF, decoded up front
+///
+/// The LZW stream of each frame is independent of the others, so [`decode_frames`] runs them on
+/// the rayon thread pool. Putting the frames together is inherently sequential and is left to
+/// [`DecodedGif::composite`].
+pub struct DecodedGif {
+    size: Size,
+    frames: Vec<DecodedFrame>,
+}
+
+/// Palette indices and drawing information of a single frame
+pub struct DecodedFrame {
+    indices: Vec<u8>,
+    frame_area: Rectangle,
+    palette: Vec<Rgb888>,
+    transparent_idx: Option<u8>,
+    disposal: DisposalMethod,
+}
+
+/// Decodes all frames of `gif` in parallel
+pub fn decode_frames(gif: &[u8]) -> Result<DecodedGif, ParseError> {
+    let mut bytes = ByteIterator::from_slice(gif);
+    let info = GifInfo::parser(&mut bytes)?;
+    let frames = (0..info.num_images())
+        .into_par_iter()
+        .map(|idx| decode_frame(gif, &info, idx))
+        .collect::<Result<_, _>>()?;
+
+    Ok(DecodedGif {
+        size: info.base_size(),
+        frames,
+    })
+}
+
+fn decode_frame(gif: &[u8], info: &GifInfo, idx: usize) -> Result<DecodedFrame, ParseError> {
+    let mut bytes = ByteIterator::from_slice(gif);
+    bytes.seek_to(info.frame_offset(idx).ok_or(ParseError::NoImagesLeft)?)?;
+    let descriptor = LocalImageDescriptor::parser(&mut bytes, &NoTransform)?;
+    let color_table = descriptor
+        .color_table()
+        .or_else(|| info.global_table())
+        .ok_or(ParseError::BadGifFile)?;
+    let decoder = RefCell::new(Decoder::new(8));
+
+    let mut frame: GifFrame<'_, _, DEFAULT_DECODE_BUFFER_SIZE, _> = GifFrame::new(
+        bytes,
+        color_table,
+        &descriptor,
+        info.frame_control(idx),
+        decoder.borrow_mut(),
+        Some(Rgb565::BLACK),
+        descriptor.bounding_box(),
+    );
+    let mut indices = vec![0; frame.num_pixels()];
+    frame.decode_indices(&mut indices)?;
+
+    Ok(DecodedFrame {
+        indices,
+        frame_area: frame.frame_area(),
+        palette: frame.palette_rgb().collect(),
+        transparent_idx: frame.transparent_index(),
+        disposal: frame.disposal_method(),
+    })
+}
+
+impl DecodedGif {
+    /// Size of the logical screen
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn frames(&self) -> &[DecodedFrame] {
+        &self.frames
+    }
+
+    /// Composites the frames in order, returning the full RGBA screen after each one
+    ///
+    /// The screen starts out filled with `background`, and [`DisposalMethod::OverwriteWithBG`]
+    /// clears back to it, giving the same screens as a [`Compositor`] created with that color.
+    /// Browsers clear to transparent instead, which a `background` of `[0; 4]` does. Frames are
+    /// clipped to the logical screen.
+    ///
+    /// [`Compositor`]: crate::Compositor
+    pub fn composite(&self, background: [u8; 4]) -> Vec<Vec<[u8; 4]>> {
+        let width = self.size.width as usize;
+        let screen = Rectangle::new(Point::zero(), self.size);
+        let mut canvas = vec![background; width * self.size.height as usize];
+        let mut screens = Vec::with_capacity(self.frames.len());
+        // Area cleared before the next frame, along with the canvas to restore it from
+        let mut pending: Option<(Rectangle, Option<Vec<[u8; 4]>>)> = None;
+
+        for frame in &self.frames {
+            match pending.take() {
+                Some((_, Some(saved))) => canvas = saved,
+                Some((area, None)) => {
+                    for point in area.points() {
+                        canvas[point.y as usize * width + point.x as usize] = background;
+                    }
+                }
+                None => {}
+            }
+
+            let area = frame.frame_area.intersection(&screen);
+            let saved =
+                (frame.disposal == DisposalMethod::OverwriteWithPrev).then(|| canvas.clone());
+            for (point, rgba) in frame.rgba_pixels() {
+                // Transparent pixels are the only ones with an alpha of 0
+                if rgba[3] != 0 && area.contains(point) {
+                    canvas[point.y as usize * width + point.x as usize] = rgba;
+                }
+            }
+            screens.push(canvas.clone());
+
+            pending = match frame.disposal {
+                DisposalMethod::OverwriteWithBG => Some((area, None)),
+                DisposalMethod::OverwriteWithPrev => Some((area, saved)),
+                _ => None,
+            };
+        }
+        screens
+    }
+}
+
+impl DecodedFrame {
+    /// Row-major palette indices covering [`Self::frame_area`]
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// Area the frame declares it covers
+    pub fn frame_area(&self) -> Rectangle {
+        self.frame_area
+    }
+
+    /// Color table the indices refer to
+    pub fn palette(&self) -> &[Rgb888] {
+        &self.palette
+    }
+
+    /// Palette index that is left undrawn, if any
+    pub fn transparent_index(&self) -> Option<u8> {
+        self.transparent_idx
+    }
+
+    pub fn disposal_method(&self) -> DisposalMethod {
+        self.disposal
+    }
+
+    /// The frame on its own as row-major RGBA, transparent pixels have an alpha of 0
+    ///
+    /// Indices past the end of the palette are black, matching [`GifFrameStreamer`]'s default
+    /// fallback color.
+    ///
+    /// [`GifFrameStreamer`]: crate::GifFrameStreamer
+    pub fn to_rgba(&self) -> Vec<[u8; 4]> {
+        self.rgba_pixels().map(|(_, rgba)| rgba).collect()
+    }
+
+    fn rgba_pixels(&self) -> impl Iterator<Item = (Point, [u8; 4])> + '_ {
+        let width = self.frame_area.size.width.max(1) as usize;
+        let origin = self.frame_area.top_left;
+        self.indices.iter().enumerate().map(move |(n, &idx)| {
+            let point = origin + Point::new((n % width) as i32, (n / width) as i32);
+            let rgba = if Some(idx) == self.transparent_idx {
+                [0; 4]
+            } else {
+                let color = self.palette.get(idx as usize).copied();
+                let color = color.unwrap_or(Rgb888::BLACK);
+                [color.r(), color.g(), color.b(), 255]
+            };
+            (point, rgba)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{encode_gif, Framebuffer, TestFrame, PALETTE};
+    use crate::{Compositor, GifFrameStreamer};
+    use embedded_graphics::draw_target::DrawTarget;
+
+    fn sequential_indices(gif: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = GifFrameStreamer::from_slice(gif).unwrap();
+        (0..frames.num_images())
+            .map(|_| {
+                let mut frame = frames.next_frame().unwrap();
+                let mut indices = vec![0; frame.num_pixels()];
+                frame.decode_indices(&mut indices).unwrap();
+                indices
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_sequential_decoding() {
+        for gif in [
+            &include_bytes!("test/crab.gif")[..],
+            &include_bytes!("test/bee.gif")[..],
+        ] {
+            let decoded = decode_frames(gif).unwrap();
+            let parallel: Vec<Vec<u8>> = decoded
+                .frames()
+                .iter()
+                .map(|frame| frame.indices().to_vec())
+                .collect();
+            assert_eq!(parallel, sequential_indices(gif));
+        }
+    }
+
+    #[test]
+    fn rgba_keeps_file_colors() {
+        // Colors whose low bits don't survive a trip through Rgb565
+        let colors = [[100, 50, 25], [3, 7, 250]];
+        let mut frame = TestFrame::new((3, 1), vec![0, 1, 2]);
+        frame.transparent = Some(1);
+        let gif = encode_gif((3, 1), &colors, &[frame]);
+
+        let decoded = decode_frames(&gif).unwrap();
+        let frame = &decoded.frames()[0];
+        assert_eq!(
+            frame.palette(),
+            [Rgb888::new(100, 50, 25), Rgb888::new(3, 7, 250)]
+        );
+        let (brown, black) = ([100, 50, 25, 255], [0, 0, 0, 255]);
+        assert_eq!(frame.to_rgba(), [brown, [0; 4], black]);
+        let screens = decoded.composite([1, 2, 3, 4]);
+        assert_eq!(screens, [[brown, [1, 2, 3, 4], black]]);
+    }
+
+    #[test]
+    fn composite_disposal() {
+        let base = TestFrame::new((2, 1), vec![1, 1]);
+        let mut restore_prev = TestFrame::new((1, 1), vec![2]);
+        restore_prev.disposal = 3;
+        let mut restore_bg = TestFrame::new((1, 1), vec![3]);
+        restore_bg.origin = (1, 0);
+        restore_bg.disposal = 2;
+        let mut overlay = TestFrame::new((2, 1), vec![0, 2]);
+        overlay.transparent = Some(0);
+        let gif = encode_gif((2, 1), &PALETTE, &[base, restore_prev, restore_bg, overlay]);
+
+        let screens = decode_frames(&gif).unwrap().composite([0; 4]);
+        let (red, green, blue) = ([255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]);
+        assert_eq!(screens[0], [red, red]);
+        assert_eq!(screens[1], [green, red]);
+        // The green pixel was restored to red before drawing the blue one
+        assert_eq!(screens[2], [red, blue]);
+        // The blue pixel was cleared and the transparent pixel leaves the red one in place
+        assert_eq!(screens[3], [red, green]);
+    }
+
+    #[test]
+    fn composite_matches_compositor() {
+        let mut cleared = TestFrame::new((2, 1), vec![3, 3]);
+        cleared.origin = (1, 1);
+        cleared.disposal = 2;
+        let mut restored = TestFrame::new((2, 2), vec![2, 0, 0, 2]);
+        restored.transparent = Some(0);
+        restored.disposal = 3;
+        let gif = encode_gif(
+            (3, 2),
+            &PALETTE,
+            &[
+                TestFrame::new((2, 2), vec![1; 4]),
+                cleared,
+                restored,
+                TestFrame::new((1, 1), vec![0]),
+            ],
+        );
+        let background = Rgb888::new(255, 255, 0);
+
+        let mut frames = GifFrameStreamer::from_slice(&gif).unwrap();
+        let mut compositor = Compositor::new(background.into());
+        let mut fb = Framebuffer::<3, 2>::new();
+        fb.clear(background.into()).unwrap();
+        let mut save = vec![Rgb565::BLACK; frames.restore_buffer_len()];
+        let sequential: Vec<Vec<Rgb565>> = (0..frames.num_images())
+            .map(|_| {
+                let mut frame = frames.next_frame().unwrap();
+                compositor
+                    .draw_frame_restoring(&mut frame, &mut fb, &mut save)
+                    .unwrap();
+                Rectangle::new(Point::zero(), Size::new(3, 2))
+                    .points()
+                    .map(|point| fb.pixel(point))
+                    .collect()
+            })
+            .collect();
+
+        let rgba = [background.r(), background.g(), background.b(), 255];
+        let parallel: Vec<Vec<Rgb565>> = decode_frames(&gif)
+            .unwrap()
+            .composite(rgba)
+            .iter()
+            .map(|screen| {
+                screen
+                    .iter()
+                    .map(|&[r, g, b, _]| Rgb888::new(r, g, b).into())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(parallel, sequential);
+    }
+}
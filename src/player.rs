@@ -0,0 +1,631 @@
+use embedded_graphics::{
+    draw_target::DrawTarget, pixelcolor::Rgb565, prelude::Point, primitives::Rectangle,
+};
+
+use crate::{
+    common::ParseError,
+    compositor::{CompositeError, Compositor, ReadbackTarget},
+    frame::{GifFrame, GifFrameStreamer, DEFAULT_DECODE_BUFFER_SIZE},
+    iterators::SeekableIter,
+    lzw::{Decoder, LzwDecoder},
+    palette::{NoOverrides, NoTransform, PaletteOverrides, PaletteTransform},
+};
+
+/// Frame delays below this many milliseconds are replaced with [`SHORT_DELAY_MS`]
+///
+/// Browsers do the same, since files with tiny or zero delays were made expecting it.
+pub const MIN_DELAY_MS: u32 = 20;
+/// Delay used for frames that don't specify one, or specify one below [`MIN_DELAY_MS`]
+pub const SHORT_DELAY_MS: u32 = 100;
+
+/// Number of times an animation is played
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Loops {
+    /// Start over after the last frame, without ever stopping
+    Forever,
+    /// Play this many times, then stay on the last frame
+    Times(u32),
+}
+
+/// Order in which [`Player`] goes through the frames
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// First to last frame, starting over at the first one
+    Forward,
+    /// Last to first frame, starting over at the last one
+    Reverse,
+    /// First to last frame and back again, where one play is a pass there and back
+    PingPong,
+}
+
+/// What the caller should do after [`Player::tick`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tick {
+    /// A new frame was loaded and should be drawn
+    pub redraw: bool,
+    /// Time at which the next frame is due; `None` while paused or once the animation is over
+    ///
+    /// Nothing changes before then, so the caller can sleep until this deadline.
+    pub next_deadline: Option<u64>,
+}
+
+/// Plays an animation back according to its frame delays and loop count
+///
+/// The player doesn't keep time itself, every call to [`Player::tick`] passes in the current
+/// time in milliseconds from any monotonic clock. Each tick moves on by at most one frame, so
+/// frames are never skipped; when the caller falls behind, the next deadline is pushed out
+/// rather than racing through frames to catch up.
+///
+/// Playback can be paused, sped up or slowed down, reversed and stepped through frame by frame.
+/// [`Player::draw`] composites the shown frame onto a target, going back to the nearest key
+/// frame whenever the shown frame doesn't directly follow the one drawn before.
+pub struct Player<
+    S,
+    const DECODE_BUF: usize = DEFAULT_DECODE_BUFFER_SIZE,
+    L = Decoder,
+    T = NoTransform,
+    P = NoOverrides,
+> where
+    S: SeekableIter,
+    L: LzwDecoder,
+    T: PaletteTransform,
+    P: PaletteOverrides,
+{
+    streamer: GifFrameStreamer<S, DECODE_BUF, L, T, P>,
+    loops: Loops,
+    /// Number of times the animation was played to the end
+    plays: u32,
+    /// Time at which the shown frame is replaced, `None` before the first frame is shown
+    deadline: Option<u64>,
+    finished: bool,
+    direction: Direction,
+    /// Whether frames are currently gone through from last to first
+    backwards: bool,
+    /// Playback speed in percent of the speed the file asks for
+    speed: u32,
+    /// Time left on the shown frame when playback was paused
+    paused: Option<u64>,
+    /// Index of the frame last composited by [`Self::draw`]
+    drawn: Option<usize>,
+}
+
+impl<S, const DECODE_BUF: usize, L, T, P> Player<S, DECODE_BUF, L, T, P>
+where
+    S: SeekableIter,
+    L: LzwDecoder,
+    T: PaletteTransform,
+    P: PaletteOverrides,
+{
+    /// Plays the frames of `streamer` as many times as the file asks for
+    ///
+    /// See [`GifFrameStreamer::loop_count`]. A loop count of `n` plays the animation `n + 1`
+    /// times, as browsers do.
+    pub fn new(streamer: GifFrameStreamer<S, DECODE_BUF, L, T, P>) -> Self {
+        let loops = match streamer.loop_count() {
+            None => Loops::Times(1),
+            Some(0) => Loops::Forever,
+            Some(count) => Loops::Times(count as u32 + 1),
+        };
+        Self {
+            streamer,
+            loops,
+            plays: 0,
+            deadline: None,
+            finished: false,
+            direction: Direction::Forward,
+            backwards: false,
+            speed: 100,
+            paused: None,
+            drawn: None,
+        }
+    }
+
+    /// Overrides the number of times the animation is played
+    pub fn set_loops(&mut self, loops: Loops) {
+        self.loops = loops;
+    }
+
+    pub fn loops(&self) -> Loops {
+        self.loops
+    }
+
+    /// Changes the order frames are played in, carrying on from the shown frame
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+        match direction {
+            Direction::Forward => self.backwards = false,
+            Direction::Reverse => self.backwards = true,
+            Direction::PingPong => {}
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Plays at `percent` of the speed the file asks for, e.g. 50 for half and 200 for double
+    /// speed
+    ///
+    /// Frame delays are divided by the speed after [`MIN_DELAY_MS`] is applied, so that fast
+    /// playback stays in proportion. A speed of 0 is treated as 1.
+    pub fn set_speed_percent(&mut self, percent: u32) {
+        self.speed = percent.max(1);
+    }
+
+    pub fn speed_percent(&self) -> u32 {
+        self.speed
+    }
+
+    /// Stops the animation at time `now_ms`, until [`Self::resume`] is called
+    ///
+    /// The time left on the shown frame is kept, so it is shown for its full delay overall.
+    pub fn pause(&mut self, now_ms: u64) {
+        if self.paused.is_none() {
+            let left = self
+                .deadline
+                .map_or(0, |deadline| deadline.saturating_sub(now_ms));
+            self.paused = Some(left);
+        }
+    }
+
+    /// Carries on at time `now_ms` with what was left of the shown frame's delay
+    pub fn resume(&mut self, now_ms: u64) {
+        if let Some(left) = self.paused.take() {
+            if self.deadline.is_some() {
+                self.deadline = Some(now_ms + left);
+            }
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Advances the animation to time `now_ms`
+    ///
+    /// The first tick loads the first frame. After that a frame is loaded whenever the deadline
+    /// of the shown one has passed, until the animation has been played [`Self::loops`] times.
+    /// Nothing happens while paused.
+    pub fn tick(&mut self, now_ms: u64) -> Result<Tick, ParseError> {
+        if self.finished || self.paused.is_some() {
+            return Ok(Tick {
+                redraw: false,
+                next_deadline: None,
+            });
+        }
+        let deadline = match self.deadline {
+            None => {
+                let first = if self.backwards {
+                    self.streamer.num_images().saturating_sub(1)
+                } else {
+                    0
+                };
+                self.show_frame(first)?;
+                return Ok(self.schedule(now_ms, now_ms, first));
+            }
+            Some(deadline) if now_ms < deadline => {
+                return Ok(Tick {
+                    redraw: false,
+                    next_deadline: Some(deadline),
+                })
+            }
+            Some(deadline) => deadline,
+        };
+
+        let next = match self.next_index() {
+            Some(next) => next,
+            None => {
+                self.finished = true;
+                return Ok(Tick {
+                    redraw: false,
+                    next_deadline: None,
+                });
+            }
+        };
+        self.show_frame(next)?;
+        Ok(self.schedule(deadline, now_ms, next))
+    }
+
+    /// Index of the frame after the shown one, or `None` if the animation is played out
+    fn next_index(&mut self) -> Option<usize> {
+        let count = self.streamer.num_images();
+        let idx = self.frame_index();
+        let next = if self.backwards {
+            idx.checked_sub(1)
+        } else {
+            Some(idx + 1).filter(|&next| next < count)
+        };
+        if next.is_some() {
+            return next;
+        }
+
+        // Looping a still image would only redraw the same frame
+        if count <= 1 {
+            return None;
+        }
+        // A ping-pong pass only ends back at the first frame
+        let bounce = self.direction == Direction::PingPong;
+        if !bounce || self.backwards {
+            self.plays = self.plays.saturating_add(1);
+            let played_out = match self.loops {
+                Loops::Forever => false,
+                Loops::Times(times) => self.plays >= times,
+            };
+            if played_out {
+                return None;
+            }
+        }
+        if bounce {
+            self.backwards = !self.backwards;
+            Some(if self.backwards { idx - 1 } else { idx + 1 })
+        } else if self.backwards {
+            Some(count - 1)
+        } else {
+            Some(0)
+        }
+    }
+
+    /// Sets the deadline of the just loaded `idx`th frame, counting from the previous deadline
+    fn schedule(&mut self, start: u64, now_ms: u64, idx: usize) -> Tick {
+        let delay = self.scaled_delay_ms(idx);
+        let mut deadline = start + delay;
+        if deadline <= now_ms {
+            deadline = now_ms + delay;
+        }
+        self.deadline = Some(deadline);
+        Tick {
+            redraw: true,
+            next_deadline: Some(deadline),
+        }
+    }
+
+    fn show_frame(&mut self, idx: usize) -> Result<(), ParseError> {
+        self.streamer.seek_to_index(idx)
+    }
+
+    /// Shows the frame after the shown one and pauses, wrapping around after the last frame
+    ///
+    /// Stepping ignores the direction and loop count. Resuming carries on from the stepped to
+    /// frame.
+    pub fn step_forward(&mut self) -> Result<(), ParseError> {
+        let count = self.streamer.num_images();
+        let next = match self.deadline {
+            None => 0,
+            Some(_) => (self.frame_index() + 1) % count.max(1),
+        };
+        self.step_to(next)
+    }
+
+    /// Shows the frame before the shown one and pauses, wrapping around before the first frame
+    ///
+    /// The frame is composited correctly by [`Self::draw`], which goes back to the nearest key
+    /// frame to do so.
+    pub fn step_backward(&mut self) -> Result<(), ParseError> {
+        let count = self.streamer.num_images();
+        let next = match self.deadline {
+            Some(_) if self.frame_index() > 0 => self.frame_index() - 1,
+            _ => count.saturating_sub(1),
+        };
+        self.step_to(next)
+    }
+
+    fn step_to(&mut self, idx: usize) -> Result<(), ParseError> {
+        self.show_frame(idx)?;
+        self.paused = Some(self.scaled_delay_ms(idx));
+        self.deadline = Some(self.deadline.unwrap_or(0));
+        self.finished = false;
+        Ok(())
+    }
+
+    /// Time the `idx`th frame is shown for, in milliseconds
+    pub fn delay_ms(&self, idx: usize) -> u32 {
+        match self.streamer.frame_delay_ms(idx) {
+            Some(delay) if delay >= MIN_DELAY_MS => delay,
+            _ => SHORT_DELAY_MS,
+        }
+    }
+
+    /// [`Self::delay_ms`] at the playback speed, at least a millisecond
+    fn scaled_delay_ms(&self, idx: usize) -> u64 {
+        (self.delay_ms(idx) as u64 * 100 / self.speed as u64).max(1)
+    }
+
+    /// Whether the animation has been played [`Self::loops`] times
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Plays the animation again from the start, with the next tick loading the first frame
+    pub fn restart(&mut self) {
+        self.plays = 0;
+        self.deadline = None;
+        self.finished = false;
+        self.backwards = self.direction == Direction::Reverse;
+    }
+
+    /// Index of the frame that is shown
+    pub fn frame_index(&self) -> usize {
+        self.streamer.frame_index().unwrap_or(0)
+    }
+
+    /// The frame that is shown, to draw after a tick asks for a redraw
+    pub fn current_frame(&self) -> Result<GifFrame<'_, S, DECODE_BUF, L>, ParseError> {
+        self.streamer.current_frame()
+    }
+
+    /// Composites the shown frame onto `target`, returning the area of the screen that changed
+    ///
+    /// Pass the same compositor and target every time. When the shown frame directly follows
+    /// the one drawn last, only the shown frame is drawn and the changed area is
+    /// [`GifFrameStreamer::dirty_area`]. Otherwise, e.g. when playing in reverse or after
+    /// stepping back, the whole screen is composited from the nearest key frame on like
+    /// [`GifFrameStreamer::seek_to_frame`] does.
+    pub fn draw<D>(
+        &mut self,
+        compositor: &mut Compositor,
+        target: &mut D,
+    ) -> Result<Rectangle, CompositeError<D::Error>>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        self.composite(compositor, target, |compositor, frame, target| {
+            compositor.draw_frame(frame, target)
+        })
+    }
+
+    /// Same as [`Self::draw`], also handling frames that are restored to the previous contents,
+    /// see [`Compositor::draw_frame_restoring`]
+    pub fn draw_restoring<D>(
+        &mut self,
+        compositor: &mut Compositor,
+        target: &mut D,
+        save: &mut [Rgb565],
+    ) -> Result<Rectangle, CompositeError<D::Error>>
+    where
+        D: ReadbackTarget<Color = Rgb565>,
+    {
+        self.composite(compositor, target, |compositor, frame, target| {
+            compositor.draw_frame_restoring(frame, target, save)
+        })
+    }
+
+    fn composite<D, F>(
+        &mut self,
+        compositor: &mut Compositor,
+        target: &mut D,
+        mut draw: F,
+    ) -> Result<Rectangle, CompositeError<D::Error>>
+    where
+        D: DrawTarget<Color = Rgb565>,
+        F: FnMut(
+            &mut Compositor,
+            &mut GifFrame<'_, S, DECODE_BUF, L>,
+            &mut D,
+        ) -> Result<(), CompositeError<D::Error>>,
+    {
+        let shown = match self.streamer.frame_index() {
+            Some(shown) => shown,
+            None => return Ok(Rectangle::zero()),
+        };
+        let dirty = if self.drawn.map(|drawn| drawn + 1) == Some(shown) {
+            let dirty = self
+                .streamer
+                .dirty_area(shown)
+                .map_err(CompositeError::Decode)?;
+            let mut frame = self
+                .streamer
+                .current_frame()
+                .map_err(CompositeError::Decode)?;
+            draw(compositor, &mut frame, target)?;
+            dirty
+        } else {
+            self.drawn = None;
+            self.streamer
+                .composite_up_to(shown, compositor, target, &mut draw)?;
+            Rectangle::new(Point::zero(), self.streamer.base_size())
+        };
+        self.drawn = Some(shown);
+        Ok(dirty)
+    }
+
+    pub fn streamer(&self) -> &GifFrameStreamer<S, DECODE_BUF, L, T, P> {
+        &self.streamer
+    }
+
+    pub fn streamer_mut(&mut self) -> &mut GifFrameStreamer<S, DECODE_BUF, L, T, P> {
+        &mut self.streamer
+    }
+
+    pub fn into_inner(self) -> GifFrameStreamer<S, DECODE_BUF, L, T, P> {
+        self.streamer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{encode_gif, insert_loop_count, Framebuffer, TestFrame, PALETTE};
+    use embedded_graphics::prelude::{RgbColor, Size};
+
+    fn frames(delays: &[u16]) -> std::vec::Vec<u8> {
+        let frames: std::vec::Vec<TestFrame> = delays
+            .iter()
+            .map(|&delay| {
+                let mut frame = TestFrame::new((1, 1), vec![0]);
+                frame.delay = delay;
+                frame
+            })
+            .collect();
+        encode_gif((1, 1), &[[0, 0, 0], [255, 255, 255]], &frames)
+    }
+
+    fn tick(redraw: bool, next_deadline: Option<u64>) -> Tick {
+        Tick {
+            redraw,
+            next_deadline,
+        }
+    }
+
+    #[test]
+    fn frame_delays() {
+        // The 1 hundredth of a second delay gets bumped up to the short delay
+        let gif = frames(&[5, 1, 20]);
+        let mut player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        assert_eq!(player.loops(), Loops::Times(1));
+
+        assert_eq!(player.tick(1000).unwrap(), tick(true, Some(1050)));
+        assert_eq!(player.frame_index(), 0);
+        assert_eq!(player.tick(1049).unwrap(), tick(false, Some(1050)));
+        assert_eq!(player.tick(1060).unwrap(), tick(true, Some(1150)));
+        assert_eq!(player.frame_index(), 1);
+        // Falling behind pushes the deadline out
+        assert_eq!(player.tick(1400).unwrap(), tick(true, Some(1600)));
+        assert_eq!(player.frame_index(), 2);
+
+        assert_eq!(player.tick(1600).unwrap(), tick(false, None));
+        assert!(player.is_finished());
+        assert_eq!(player.frame_index(), 2);
+
+        player.restart();
+        assert_eq!(player.tick(2000).unwrap(), tick(true, Some(2050)));
+        assert_eq!(player.frame_index(), 0);
+    }
+
+    #[test]
+    fn loop_count() {
+        let mut gif = frames(&[10, 10]);
+        insert_loop_count(&mut gif, 1);
+        let mut player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        assert_eq!(player.loops(), Loops::Times(2));
+
+        let shown: std::vec::Vec<usize> = (0..5)
+            .map(|n| {
+                player.tick(n * 100).unwrap();
+                player.frame_index()
+            })
+            .collect();
+        assert_eq!(shown, [0, 1, 0, 1, 1]);
+        assert!(player.is_finished());
+
+        let mut gif = frames(&[10, 10]);
+        insert_loop_count(&mut gif, 0);
+        let player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        assert_eq!(player.loops(), Loops::Forever);
+    }
+
+    #[test]
+    fn pause_and_speed() {
+        let gif = frames(&[10, 10, 10]);
+        let mut player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        assert_eq!(player.tick(0).unwrap(), tick(true, Some(100)));
+
+        player.pause(40);
+        assert!(player.is_paused());
+        assert_eq!(player.tick(500).unwrap(), tick(false, None));
+        // The 60ms left on the first frame count from resuming
+        player.resume(1000);
+        assert_eq!(player.tick(1059).unwrap(), tick(false, Some(1060)));
+        assert_eq!(player.tick(1060).unwrap(), tick(true, Some(1160)));
+        assert_eq!(player.frame_index(), 1);
+
+        player.set_speed_percent(200);
+        assert_eq!(player.tick(1160).unwrap(), tick(true, Some(1210)));
+        assert_eq!(player.frame_index(), 2);
+        player.set_speed_percent(50);
+        player.restart();
+        assert_eq!(player.tick(2000).unwrap(), tick(true, Some(2200)));
+    }
+
+    #[test]
+    fn directions() {
+        let gif = frames(&[10, 10, 10, 10]);
+        let mut player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        let play = |player: &mut Player<_>, ticks: u64| {
+            (0..ticks)
+                .map(|n| {
+                    player.tick(n * 100).unwrap();
+                    player.frame_index()
+                })
+                .collect::<std::vec::Vec<usize>>()
+        };
+
+        player.set_direction(Direction::Reverse);
+        player.set_loops(Loops::Forever);
+        assert_eq!(play(&mut player, 6), [3, 2, 1, 0, 3, 2]);
+
+        player.set_direction(Direction::PingPong);
+        player.set_loops(Loops::Times(1));
+        player.restart();
+        assert_eq!(play(&mut player, 9), [0, 1, 2, 3, 2, 1, 0, 0, 0]);
+        assert!(player.is_finished());
+
+        player.step_backward().unwrap();
+        assert_eq!(player.frame_index(), 3);
+        assert!(player.is_paused() && !player.is_finished());
+        player.step_forward().unwrap();
+        assert_eq!(player.frame_index(), 0);
+
+        // A still image has nowhere to bounce to
+        let gif = frames(&[10]);
+        let mut player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        player.set_direction(Direction::PingPong);
+        player.set_loops(Loops::Forever);
+        assert_eq!(play(&mut player, 3), [0, 0, 0]);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn draw_backwards() {
+        let mut cleared = TestFrame::new((1, 1), vec![3]);
+        cleared.origin = (1, 0);
+        cleared.disposal = 2;
+        let gif = encode_gif(
+            (2, 1),
+            &PALETTE,
+            &[
+                TestFrame::new((2, 1), vec![1, 1]),
+                TestFrame::new((1, 1), vec![2]),
+                cleared,
+                TestFrame::new((1, 1), vec![3]),
+            ],
+        );
+        let mut player = Player::new(GifFrameStreamer::from_slice(&gif).unwrap());
+        let mut compositor = Compositor::new(Rgb565::WHITE);
+        let mut fb = Framebuffer::<2, 1>::new();
+        let mut draw = |player: &mut Player<_>| {
+            let area = player.draw(&mut compositor, &mut fb).unwrap();
+            (
+                area,
+                [fb.pixel(Point::new(0, 0)), fb.pixel(Point::new(1, 0))],
+            )
+        };
+        let (red, green, blue) = (Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE);
+        let screens = [
+            [red, red],
+            [green, red],
+            [green, blue],
+            [blue, Rgb565::WHITE],
+        ];
+
+        let rect = |x, width| Rectangle::new(Point::new(x, 0), Size::new(width, 1));
+        // After the first frame, only the frame itself and the cleared pixel change
+        let areas = [rect(0, 2), rect(0, 1), rect(1, 1), rect(0, 2)];
+        for (screen, area) in screens.iter().zip(areas) {
+            player.step_forward().unwrap();
+            assert_eq!(draw(&mut player), (area, *screen));
+        }
+
+        for idx in (0..3).rev() {
+            player.step_backward().unwrap();
+            assert_eq!(
+                draw(&mut player),
+                (rect(0, 2), screens[idx]),
+                "frame {}",
+                idx
+            );
+        }
+        // Wrapping around from the first to the last frame
+        player.step_backward().unwrap();
+        assert_eq!(draw(&mut player).1, screens[3]);
+    }
+}
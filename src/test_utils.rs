@@ -1,5 +1,6 @@
-use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
-
+use crate::iterators::{SeekableIter, SeekableSliceIter};
+use crate::ReadbackTarget;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
 
 // TODO: use e-g framebuffer when it's added
 pub(crate) struct Framebuffer<const WIDTH: usize, const HEIGHT: usize> {
@@ -14,6 +15,10 @@ impl<const WIDTH: usize, const HEIGHT: usize> Framebuffer<WIDTH, HEIGHT> {
             pixels: [[color; WIDTH]; HEIGHT],
         }
     }
+
+    pub fn pixel(&self, point: Point) -> Rgb565 {
+        self.pixels[point.y as usize][point.x as usize]
+    }
 }
 
 impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for Framebuffer<WIDTH, HEIGHT> {
@@ -32,8 +37,177 @@ impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for Framebuffer<WIDTH,
     }
 }
 
+impl<const WIDTH: usize, const HEIGHT: usize> ReadbackTarget for Framebuffer<WIDTH, HEIGHT> {
+    fn read_area(&mut self, area: &Rectangle, buf: &mut [Rgb565]) -> Result<(), Self::Error> {
+        for (pixel, point) in buf.iter_mut().zip(area.points()) {
+            *pixel = self.pixel(point);
+        }
+        Ok(())
+    }
+}
+
 impl<const WIDTH: usize, const HEIGHT: usize> OriginDimensions for Framebuffer<WIDTH, HEIGHT> {
     fn size(&self) -> embedded_graphics::prelude::Size {
-        Size::new(240, 320)
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+/// Source that can't hand out slices, so readers have to copy bytes out of it
+#[derive(Clone)]
+pub(crate) struct CopyingIter<'a>(SeekableSliceIter<'a>);
+
+impl<'a> CopyingIter<'a> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        Self(SeekableSliceIter::new(slice))
     }
 }
+
+impl Iterator for CopyingIter<'_> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl SeekableIter for CopyingIter<'_> {
+    fn seek(&mut self, offset: usize) -> Result<(), usize> {
+        self.0.seek(offset)
+    }
+    fn move_by(&mut self, len: usize) -> Result<(), usize> {
+        self.0.move_by(len)
+    }
+}
+
+/// Black, red, green and blue; a global table for [`encode_gif`] with easily told apart colors
+pub(crate) const PALETTE: [[u8; 3]; 4] = [[0, 0, 0], [255, 0, 0], [0, 255, 0], [0, 0, 255]];
+
+/// Description of a single frame for [`encode_gif`]
+pub(crate) struct TestFrame {
+    pub origin: (u16, u16),
+    pub size: (u16, u16),
+    /// Palette indices, in row-major order
+    pub indices: Vec<u8>,
+    /// LZW minimum code size
+    pub code_size: u8,
+    pub local_table: Option<Vec<[u8; 3]>>,
+    /// Store rows in interlaced order
+    pub interlaced: bool,
+    /// Transparent palette index, written to a graphics control extension
+    pub transparent: Option<u8>,
+    /// Disposal method field of the graphics control extension
+    pub disposal: u8,
+    /// Delay time of the graphics control extension, in hundredths of a second
+    pub delay: u16,
+}
+
+impl TestFrame {
+    pub fn new(size: (u16, u16), indices: Vec<u8>) -> Self {
+        Self {
+            origin: (0, 0),
+            size,
+            indices,
+            code_size: 8,
+            local_table: None,
+            interlaced: false,
+            transparent: None,
+            disposal: 0,
+            delay: 0,
+        }
+    }
+}
+
+fn table_bits(table: &[[u8; 3]]) -> u8 {
+    assert!(table.len().is_power_of_two() && table.len() >= 2);
+    table.len().trailing_zeros() as u8 - 1
+}
+
+fn push_table(out: &mut Vec<u8>, table: &[[u8; 3]]) {
+    for entry in table {
+        out.extend_from_slice(entry);
+    }
+}
+
+fn interlace(indices: &[u8], width: usize) -> Vec<u8> {
+    let rows: Vec<&[u8]> = indices.chunks(width).collect();
+    [(0, 8), (4, 8), (2, 4), (1, 2)]
+        .iter()
+        .flat_map(|&(start, step)| rows.iter().skip(start).step_by(step))
+        .flat_map(|row| row.iter().copied())
+        .collect()
+}
+
+/// Builds a minimal GIF89a file in memory, so tests can exercise hand picked edge cases
+///
+/// An empty `global_table` leaves out the global color table.
+pub(crate) fn encode_gif(
+    size: (u16, u16),
+    global_table: &[[u8; 3]],
+    frames: &[TestFrame],
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&size.0.to_le_bytes());
+    out.extend_from_slice(&size.1.to_le_bytes());
+    if global_table.is_empty() {
+        out.push(0x70);
+    } else {
+        out.push(0xF0 | table_bits(global_table));
+    }
+    // background color index and pixel aspect ratio
+    out.extend_from_slice(&[0, 0]);
+    push_table(&mut out, global_table);
+
+    for frame in frames {
+        if frame.transparent.is_some() || frame.disposal != 0 || frame.delay != 0 {
+            let flags = (frame.disposal << 2) | frame.transparent.is_some() as u8;
+            let [delay_lo, delay_hi] = frame.delay.to_le_bytes();
+            let transparent = frame.transparent.unwrap_or(0);
+            out.extend_from_slice(&[0x21, 0xF9, 4, flags, delay_lo, delay_hi, transparent, 0]);
+        }
+
+        out.push(0x2C);
+        for val in [frame.origin.0, frame.origin.1, frame.size.0, frame.size.1] {
+            out.extend_from_slice(&val.to_le_bytes());
+        }
+        let interlace_flag = if frame.interlaced { 0x40 } else { 0 };
+        match &frame.local_table {
+            Some(table) => {
+                out.push(0x80 | interlace_flag | table_bits(table));
+                push_table(&mut out, table);
+            }
+            None => out.push(interlace_flag),
+        }
+
+        let indices = if frame.interlaced {
+            interlace(&frame.indices, frame.size.0 as usize)
+        } else {
+            frame.indices.clone()
+        };
+        out.push(frame.code_size);
+        let data = weezl::encode::Encoder::new(weezl::BitOrder::Lsb, frame.code_size)
+            .encode(&indices)
+            .unwrap();
+        for chunk in data.chunks(255) {
+            out.push(chunk.len() as u8);
+            out.extend_from_slice(chunk);
+        }
+        out.push(0);
+    }
+    out.push(0x3B);
+    out
+}
+
+/// Adds a NETSCAPE2.0 extension with `loop_count` to a file made by [`encode_gif`]
+pub(crate) fn insert_loop_count(gif: &mut Vec<u8>, loop_count: u16) {
+    let table_len = if gif[10] & 0x80 != 0 {
+        3 << ((gif[10] & 0x7) + 1)
+    } else {
+        0
+    };
+    let [count_lo, count_hi] = loop_count.to_le_bytes();
+    let mut extension = vec![0x21, 0xFF, 11];
+    extension.extend_from_slice(b"NETSCAPE2.0");
+    extension.extend_from_slice(&[3, 1, count_lo, count_hi, 0]);
+    let offset = 13 + table_len;
+    gif.splice(offset..offset, extension);
+}